@@ -0,0 +1,117 @@
+use crate::image;
+use std::io;
+
+/// Color and depth targets for one rendered image. `scale` is the
+/// supersampling factor: a scale-2 framebuffer has twice the pixels of the
+/// window on each axis and gets box-filtered down by `resolve_into`.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub scale: usize,
+    pub color: Vec<u32>,
+    pub depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::supersampled(width, height, 1)
+    }
+
+    pub fn supersampled(width: usize, height: usize, scale: usize) -> Self {
+        let (width, height) = (width * scale, height * scale);
+        Framebuffer {
+            width,
+            height,
+            scale,
+            color: vec![0; width * height],
+            depth: vec![f32::INFINITY; width * height],
+        }
+    }
+
+    pub fn clear(&mut self, color: u32) {
+        self.color.fill(color);
+        self.depth.fill(f32::INFINITY);
+    }
+
+    /// The packed `0x00RRGGBB` pixels, row by row, as minifb's
+    /// `update_with_buffer` takes them.
+    pub fn to_u32_buffer(&self) -> &[u32] {
+        &self.color
+    }
+
+    /// Writes the color target as a binary (P6) PPM.
+    pub fn save_as_ppm_binary(&self, filename: &str) -> io::Result<()> {
+        image::save_as_ppm_binary(&self.color, self.width, self.height, filename)
+    }
+
+    /// Writes the color target as a PNG.
+    pub fn save_as_png(&self, filename: &str) -> io::Result<()> {
+        image::save_as_png(&self.color, self.width, self.height, filename)
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32
+    }
+
+    /// Writes `color` at (x, y) if it passes the depth test.
+    pub fn plot(&mut self, x: i32, y: i32, z: f32, color: u32) {
+        if self.in_bounds(x, y) {
+            let idx = y as usize * self.width + x as usize;
+            if z < self.depth[idx] {
+                self.depth[idx] = z;
+                self.color[idx] = color;
+            }
+        }
+    }
+
+    /// Mixes `color` into (x, y) by `coverage` (0..=1) if it passes the depth
+    /// test. Only mostly covered pixels claim the depth, so faint fringes
+    /// don't hide what gets drawn behind them later.
+    pub fn blend(&mut self, x: i32, y: i32, z: f32, color: u32, coverage: f32) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+        let idx = y as usize * self.width + x as usize;
+        if z >= self.depth[idx] {
+            return;
+        }
+        let mix = |shift: u32| {
+            let src = ((color >> shift) & 0xFF) as f32;
+            let dst = ((self.color[idx] >> shift) & 0xFF) as f32;
+            (dst + (src - dst) * coverage.clamp(0.0, 1.0)) as u32
+        };
+        self.color[idx] = (mix(16) << 16) | (mix(8) << 8) | mix(0);
+        if coverage >= 0.5 {
+            self.depth[idx] = z;
+        }
+    }
+
+    /// Writes `color` at (x, y) ignoring depth, for overlays and backgrounds.
+    pub fn set(&mut self, x: i32, y: i32, color: u32) {
+        if self.in_bounds(x, y) {
+            let idx = y as usize * self.width + x as usize;
+            self.color[idx] = color;
+        }
+    }
+
+    /// Averages each `scale`×`scale` block into one pixel of `target`, which
+    /// must be this framebuffer's size divided by `scale`.
+    pub fn resolve_into(&self, target: &mut Framebuffer) {
+        let s = self.scale;
+        let samples = (s * s) as u32;
+        for y in 0..target.height {
+            for x in 0..target.width {
+                let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+                for sy in 0..s {
+                    let row = (y * s + sy) * self.width + x * s;
+                    for &pixel in &self.color[row..row + s] {
+                        r += (pixel >> 16) & 0xFF;
+                        g += (pixel >> 8) & 0xFF;
+                        b += pixel & 0xFF;
+                    }
+                }
+                target.color[y * target.width + x] = ((r / samples) << 16) | ((g / samples) << 8) | (b / samples);
+            }
+        }
+    }
+}
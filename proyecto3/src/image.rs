@@ -0,0 +1,296 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+fn unpack_rgb(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+/// Writes a packed `0x00RRGGBB` buffer as a binary (P6) PPM.
+pub fn save_as_ppm_binary(buffer: &[u32], width: usize, height: usize, filename: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    for &pixel in &buffer[..width * height] {
+        writer.write_all(&unpack_rgb(pixel))?;
+    }
+    writer.flush()
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut crc_input = Vec::with_capacity(4 + data.len());
+    crc_input.extend_from_slice(kind);
+    crc_input.extend_from_slice(data);
+    writer.write_all(&crc_input)?;
+    writer.write_all(&crc32(&crc_input).to_be_bytes())
+}
+
+/// Writes a packed `0x00RRGGBB` buffer as an RGBA PNG. The image data uses
+/// uncompressed deflate blocks, which keeps the encoder dependency-free.
+pub fn save_as_png(buffer: &[u32], width: usize, height: usize, filename: &str) -> io::Result<()> {
+    // Every scanline starts with filter type 0 (none)
+    let mut raw = Vec::with_capacity(height * (1 + width * 4));
+    for row in buffer[..width * height].chunks(width) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&unpack_rgb(pixel));
+            raw.push(255);
+        }
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(65535).peekable();
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        zlib.push(last as u8);
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8-bit depth, RGBA color type, default compression/filter, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writer.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'])?;
+    write_png_chunk(&mut writer, b"IHDR", &header)?;
+    write_png_chunk(&mut writer, b"IDAT", &zlib)?;
+    write_png_chunk(&mut writer, b"IEND", &[])?;
+    writer.flush()
+}
+
+fn invalid_image(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads a PPM (P3 or P6) or PNG file into a packed `0x00RRGGBB` buffer,
+/// telling the formats apart by their first bytes. Returns the pixels with
+/// the image's width and height.
+pub fn load_image(filename: &str) -> io::Result<(Vec<u32>, usize, usize)> {
+    let bytes = std::fs::read(filename)?;
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']) {
+        load_png(&bytes)
+    } else if bytes.starts_with(b"P3") || bytes.starts_with(b"P6") {
+        load_ppm(&bytes)
+    } else {
+        Err(invalid_image("not a PPM or PNG file"))
+    }
+}
+
+fn load_ppm(bytes: &[u8]) -> io::Result<(Vec<u32>, usize, usize)> {
+    // The header is whitespace-separated tokens, with `#` comments to end of line
+    let mut position = 2;
+    let mut next_token = || -> io::Result<usize> {
+        loop {
+            match bytes.get(position) {
+                Some(b'#') => {
+                    while bytes.get(position).is_some_and(|&b| b != b'\n') {
+                        position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => position += 1,
+                Some(_) => break,
+                None => return Err(invalid_image("truncated PPM")),
+            }
+        }
+        let start = position;
+        while bytes.get(position).is_some_and(|b| b.is_ascii_digit()) {
+            position += 1;
+        }
+        std::str::from_utf8(&bytes[start..position])
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .ok_or_else(|| invalid_image("invalid number in PPM"))
+    };
+
+    let width = next_token()?;
+    let height = next_token()?;
+    let max_value = next_token()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_image("invalid PPM maximum value"));
+    }
+    let samples = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| invalid_image("PPM too large"))?;
+    let scale = |sample: usize| (sample.min(max_value) * 255 / max_value) as u32;
+
+    let values: Vec<u32> = if bytes[1] == b'3' {
+        (0..samples).map(|_| next_token().map(scale)).collect::<io::Result<_>>()?
+    } else {
+        // A single whitespace byte separates the header from the raster
+        let start = position + 1;
+        let sample_size = if max_value > 255 { 2 } else { 1 };
+        let end = samples
+            .checked_mul(sample_size)
+            .and_then(|size| size.checked_add(start))
+            .ok_or_else(|| invalid_image("PPM too large"))?;
+        let raster = bytes.get(start..end).ok_or_else(|| invalid_image("truncated PPM"))?;
+        if sample_size == 2 {
+            raster.chunks(2).map(|s| scale(((s[0] as usize) << 8) | s[1] as usize)).collect()
+        } else {
+            raster.iter().map(|&s| scale(s as usize)).collect()
+        }
+    };
+
+    let pixels = values.chunks(3).map(|rgb| (rgb[0] << 16) | (rgb[1] << 8) | rgb[2]).collect();
+    Ok((pixels, width, height))
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Decodes 8-bit, non-interlaced PNGs of any color type. Alpha is dropped.
+fn load_png(bytes: &[u8]) -> io::Result<(Vec<u32>, usize, usize)> {
+    let mut position = 8;
+    let mut header = None;
+    let mut palette: Vec<u32> = Vec::new();
+    let mut compressed = Vec::new();
+    while position + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[position..position + 4].try_into().unwrap()) as usize;
+        let kind = &bytes[position + 4..position + 8];
+        let data = bytes
+            .get(position + 8..position + 8 + length)
+            .ok_or_else(|| invalid_image("truncated PNG chunk"))?;
+        match kind {
+            b"IHDR" if length == 13 => header = Some(data),
+            // At most 256 entries of three bytes each
+            b"PLTE" if !length.is_multiple_of(3) || length > 768 => return Err(invalid_image("invalid PNG palette")),
+            b"PLTE" => palette = data.chunks(3).map(|c| ((c[0] as u32) << 16) | ((c[1] as u32) << 8) | c[2] as u32).collect(),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Skip the data and its CRC
+        position += 12 + length;
+    }
+
+    let header = header.ok_or_else(|| invalid_image("PNG has no header"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+    let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if bit_depth != 8 || interlace != 0 {
+        return Err(invalid_image("only 8-bit non-interlaced PNGs are supported"));
+    }
+    let channels = match color_type {
+        0 => 1, // Gray
+        2 => 3, // RGB
+        3 => 1, // Palette index
+        4 => 2, // Gray and alpha
+        6 => 4, // RGBA
+        _ => return Err(invalid_image("unknown PNG color type")),
+    };
+
+    let raw = crate::inflate::zlib_decompress(&compressed)?;
+    let stride = width * channels;
+    let size = (stride + 1).checked_mul(height).ok_or_else(|| invalid_image("PNG too large"))?;
+    if raw.len() < size {
+        return Err(invalid_image("PNG image data is too short"));
+    }
+
+    // Undo each scanline's filter against the already decoded row above
+    let mut image = vec![0u8; height * stride];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let left = if x >= channels { image[y * stride + x - channels] } else { 0 };
+            let up = if y > 0 { image[(y - 1) * stride + x] } else { 0 };
+            let up_left = if x >= channels && y > 0 { image[(y - 1) * stride + x - channels] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(invalid_image("unknown PNG filter")),
+            };
+            image[y * stride + x] = line[x].wrapping_add(predicted);
+        }
+    }
+
+    let pixels = image
+        .chunks(channels)
+        .map(|p| match color_type {
+            0 | 4 => (p[0] as u32) * 0x010101,
+            3 => palette.get(p[0] as usize).copied().unwrap_or(0),
+            _ => ((p[0] as u32) << 16) | ((p[1] as u32) << 8) | p[2] as u32,
+        })
+        .collect();
+    Ok((pixels, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_ppm_round_trips_through_load_image() {
+        let (width, height) = (3, 2);
+        let pixels = [0xFF0000, 0x00FF00, 0x0000FF, 0x123456, 0xFFFFFF, 0x000000];
+        let path = std::env::temp_dir().join(format!("proyecto3_round_trip_{}.ppm", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        save_as_ppm_binary(&pixels, width, height, path).unwrap();
+        let loaded = load_image(path);
+        std::fs::remove_file(path).unwrap();
+
+        let (loaded, loaded_width, loaded_height) = loaded.unwrap();
+        assert_eq!((loaded_width, loaded_height), (width, height));
+        assert_eq!(loaded, pixels);
+    }
+
+    #[test]
+    fn png_palette_with_a_partial_entry_is_an_error() {
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+        png.extend_from_slice(&4u32.to_be_bytes());
+        png.extend_from_slice(b"PLTE");
+        png.extend_from_slice(&[255, 0, 0, 255, 0, 0, 0, 0]);
+        assert_eq!(load_png(&png).err().unwrap().to_string(), "invalid PNG palette");
+    }
+
+    #[test]
+    fn ppm_with_overflowing_size_is_an_error() {
+        let ppm = format!("P6\n{} {}\n255\n", usize::MAX / 2, 3);
+        assert_eq!(load_ppm(ppm.as_bytes()).err().unwrap().to_string(), "PPM too large");
+    }
+}
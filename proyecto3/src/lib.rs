@@ -0,0 +1,4187 @@
+//! Software-rendered solar system explorer. Build a `Scene` from the
+//! simulation state and hand it to `Renderer::render_frame`; `main.rs` is
+//! the windowed driver that does this every frame.
+
+pub mod font;
+pub mod framebuffer;
+pub mod image;
+mod inflate;
+mod json;
+pub mod mat4;
+pub mod obj;
+pub mod postprocess;
+pub mod rng;
+pub mod save;
+pub mod spatial;
+pub mod texture;
+pub mod triangle;
+
+pub use framebuffer::Framebuffer;
+pub use mat4::Mat4;
+pub use postprocess::ToneMapping;
+pub use spatial::SpatialGrid;
+pub use texture::Texture;
+use obj::Model;
+use rng::Rng;
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use std::ops::{Add, Mul, Neg, Sub};
+use triangle::{triangle_filled, triangle_gouraud, ScreenVertex};
+
+// Vertical field of view at startup; Z/X zoom between MIN_FOV and MAX_FOV
+const FOV: f32 = PI / 2.5;
+const MIN_FOV: f32 = PI / 18.0;
+const MAX_FOV: f32 = PI / 2.0;
+// Flying fast widens the view by up to SPEED_FOV_STRETCH radians at top
+// speed, growing with the square of the speed so cruising barely moves it.
+// It eases in and out at SPEED_FOV_RATE per second
+const SPEED_FOV_STRETCH: f32 = 0.12;
+const SPEED_FOV_RATE: f32 = 3.0;
+// Points closer to the camera than NEAR_PLANE aren't drawn; nothing is far
+// enough away to need FAR_PLANE clipping, it only shapes the clip-space z
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 5000.0;
+// Loaded meshes are rescaled to roughly the length of the built-in X-Wing
+const SHIP_MODEL_SIZE: f32 = 3.3;
+// Solid-shaded hull color, and the light it keeps on its unlit side
+const SHIP_HULL_COLOR: Color = Color { r: 216, g: 216, b: 216 };
+const SHIP_AMBIENT: f32 = 0.15;
+// Planet mass is approximated as scale³; planets beyond the range are ignored
+const GRAVITY_CONSTANT: f32 = 1.4;
+const GRAVITY_RANGE: f32 = 60.0;
+// Half the gravity range, so a gravity query spans a few cells per axis
+const SPATIAL_CELL_SIZE: f32 = 30.0;
+// N-body mode: suns are this much denser than planets, motion is integrated
+// in fixed steps of N_BODY_STEP seconds, and pairs closer than the softening
+// length pull as if they were that far apart instead of flinging each other off
+const SUN_DENSITY: f32 = 300.0;
+const N_BODY_STEP: f32 = 1.0 / 240.0;
+const N_BODY_SOFTENING: f32 = 1.0;
+// Angle step for the orbit's central-difference tangent
+const ORBIT_TANGENT_STEP: f32 = 1e-3;
+// Closest the ship's center may get to a planet's surface
+const SHIP_CLEARANCE: f32 = 2.0;
+// Surface gaps (beyond the clearance) at which the HUD warns of a closing
+// planet and the auto-brake starts capping the approach speed
+const PROXIMITY_WARNING_DISTANCE: f32 = 12.0;
+const AUTO_BRAKE_DISTANCE: f32 = 8.0;
+// A planet counts as explored once the ship is this many radii from its center
+const EXPLORE_RADIUS_FACTOR: f32 = 3.0;
+// A gravity assist needs a pass within SLINGSHOT_CLOSE_PASS of the surface
+// that turns the ship's course relative to the planet by SLINGSHOT_MIN_TURN
+// radians; the encounter is judged on leaving SLINGSHOT_RANGE
+const SLINGSHOT_RANGE: f32 = 15.0;
+const SLINGSHOT_CLOSE_PASS: f32 = 6.0;
+const SLINGSHOT_MIN_TURN: f32 = 0.35;
+const SLINGSHOT_MAX_GAIN: f32 = 1.5;
+// Fraction of the impact speed kept when bouncing off a planet
+const RESTITUTION: f32 = 0.6;
+const MAX_HEALTH: f32 = 100.0;
+// Damage grows with the square of the impact speed above SAFE_IMPACT_SPEED,
+// so grazes barely scratch the hull and a full-speed crash costs CRASH_DAMAGE
+const CRASH_DAMAGE: f32 = 45.0;
+const SAFE_IMPACT_SPEED: f32 = 0.3;
+const MAX_FUEL: f32 = 100.0;
+// Fuel burned per unit of thrust; thrust is applied once per frame, so a
+// full tank lasts about a minute of cruising and much less on afterburner
+const FUEL_PER_THRUST: f32 = 0.15;
+const FUEL_REGEN_RATE: f32 = 2.0;
+pub const WARP_FUEL_COST: f32 = 15.0;
+// Afterburner heat: about 4 s of boosting overheats it, and it stays locked
+// until it has cooled back down to BOOST_UNLOCK_HEAT
+const BOOST_HEAT_RATE: f32 = 0.25;
+const BOOST_COOL_RATE: f32 = 0.15;
+const BOOST_UNLOCK_HEAT: f32 = 0.3;
+const MAX_SPEED: f32 = 2.5;
+// Speed cap with the inertial dampeners off. Damping is what normally holds
+// the ship under `MAX_SPEED`, so Newtonian flight gets more headroom, but
+// still a cap so a long fall can't skip past a planet in one step
+const NEWTONIAN_MAX_SPEED: f32 = 10.0;
+// Fraction of its velocity the ship keeps each step with dampeners on
+const DAMPING: f32 = 0.95;
+// Most thrust cruise control applies in one step, the same as holding W
+const CRUISE_THRUST: f32 = 0.18;
+const WARP_DURATION: f32 = 1.0;
+// The autopilot lets go this far above the target's surface
+const AUTOPILOT_ARRIVAL_DISTANCE: f32 = 8.0;
+// Height above the orbital plane where the autopilot waits for its target
+const AUTOPILOT_CRUISE_ALTITUDE: f32 = 10.0;
+// Climb slope per unit of altitude still to gain
+const AUTOPILOT_CLIMB_RATE: f32 = 0.5;
+// Cruise speed per unit of remaining distance, so the ship slows as it closes in
+const AUTOPILOT_APPROACH_RATE: f32 = 0.15;
+// Seconds of travel checked ahead for collisions, and how many points along it
+const AUTOPILOT_LOOKAHEAD: f32 = 5.0;
+const AUTOPILOT_LOOKAHEAD_SAMPLES: usize = 10;
+const AUTOPILOT_THRUST: f32 = 0.18;
+// Planning speed for the intercept, kept well under the real top speed so
+// the ship gets there early and waits
+const AUTOPILOT_CRUISE_SPEED: f32 = 1.8;
+// Intercepts are searched every half second up to two minutes ahead
+const AUTOPILOT_INTERCEPT_STEP: f32 = 0.5;
+const AUTOPILOT_INTERCEPT_STEPS: usize = 240;
+// How far ahead of the ship's center the cockpit camera sits
+const COCKPIT_OFFSET: f32 = 1.2;
+// How quickly leveling out closes the pitch gap, per second; settles in
+// about half a second
+const LEVEL_RATE: f32 = 8.0;
+// Steps tried when pulling the chase camera in out of a planet, and the
+// closest it may come to the ship as a fraction of its usual offset
+const CAMERA_PULL_STEPS: usize = 20;
+const CAMERA_MIN_REACH: f32 = 0.05;
+// Camera shake is a random offset up to the current impulse on each axis,
+// in world units. The impulse falls off by SHAKE_DECAY per second, so a
+// hit settles in about half a second, and never builds past MAX_SHAKE
+const SHAKE_DECAY: f32 = 8.0;
+const MAX_SHAKE: f32 = 1.0;
+const HUD_LINE_HEIGHT: i32 = 18;
+// Wireframe thickness in window pixels
+const ORBIT_LINE_WIDTH: f32 = 1.0;
+const SHIP_LINE_WIDTH: f32 = 1.5;
+// Ecliptic grid: line spacing, how far it reaches around the camera, and
+// the distance over which it fades out
+const GRID_SPACING: f32 = 10.0;
+const GRID_EXTENT: f32 = 120.0;
+const GRID_FADE_DISTANCE: f32 = 100.0;
+const GRID_COLOR: Color = Color { r: 40, g: 70, b: 90 };
+// The predicted path covers five seconds at a nominal 60 fps, since damping
+// is applied once per step just as it is once per frame
+const TRAJECTORY_STEPS: usize = 300;
+const TRAJECTORY_STEP: f32 = 1.0 / 60.0;
+const TRAJECTORY_COLOR: Color = Color { r: 80, g: 220, b: 255 };
+// Debug spin axes stick out this many radii either side of the center
+const SPIN_AXIS_REACH: f32 = 1.5;
+const SPIN_AXIS_COLOR: u32 = 0xFF50C8;
+// Frames averaged by the F3 performance readout
+const FPS_SAMPLES: usize = 30;
+const LABEL_DISTANCE: f32 = 150.0;
+// Spheres fade linearly into the background between these camera distances
+const FOG_START: f32 = 150.0;
+const FOG_END: f32 = 250.0;
+// Spheres whose projected radius is under this many window pixels skip
+// per-pixel shading and draw as a flat disk
+const SPHERE_LOD_RADIUS: f32 = 3.0;
+const SUPERSAMPLE_FACTOR: usize = 2;
+pub const DEFAULT_VIGNETTE_STRENGTH: f32 = 0.5;
+pub const DEFAULT_ABERRATION_STRENGTH: f32 = 0.5;
+const STAR_COUNT: usize = 5000;
+// Stars twinkle by dimming up to TWINKLE_DEPTH, each on its own phase: star
+// i is TWINKLE_PHASE_STEP radians (the golden angle, so neighbors in the
+// sequence never line up) ahead of star i - 1
+const TWINKLE_DEPTH: f32 = 0.15;
+const TWINKLE_SPEED: f32 = 2.5;
+const TWINKLE_PHASE_STEP: f32 = 2.399_963;
+// Nebula clouds: fbm over the view direction at NEBULA_SCALE, clear below
+// NEBULA_THRESHOLD (about the median) and reaching NEBULA_BRIGHTNESS at
+// NEBULA_FULL. Sampled every NEBULA_SAMPLE_SPACING window pixels and
+// interpolated between
+const NEBULA_SCALE: f32 = 2.5;
+const NEBULA_THRESHOLD: f32 = 0.45;
+const NEBULA_FULL: f32 = 0.7;
+const NEBULA_BRIGHTNESS: f32 = 0.35;
+const NEBULA_SAMPLE_SPACING: usize = 4;
+const NEBULA_COLOR_A: Color = Color { r: 150, g: 70, b: 200 };
+const NEBULA_COLOR_B: Color = Color { r: 50, g: 130, b: 190 };
+// Star colors by spectral class, hottest first (O, B, A, F, G, K, M), with
+// the share of the field each gets. Skewed toward the bright classes the
+// eye actually picks out, not the red dwarfs that dominate real skies
+const SPECTRAL_CLASSES: [(f32, Color); 7] = [
+    (0.03, Color { r: 155, g: 176, b: 255 }),
+    (0.10, Color { r: 170, g: 191, b: 255 }),
+    (0.17, Color { r: 202, g: 215, b: 255 }),
+    (0.20, Color { r: 248, g: 247, b: 255 }),
+    (0.20, Color { r: 255, g: 244, b: 234 }),
+    (0.18, Color { r: 255, g: 210, b: 161 }),
+    (0.12, Color { r: 255, g: 204, b: 111 }),
+];
+// Sunlight falls off with the square of distance past SUN_LIGHT_RANGE. The
+// brightness puts Terra (r=20) at about full light, anything closer is
+// clamped there, and Viola (r=95) gets about a sixth of it
+const SUN_BRIGHTNESS: f32 = 1.3;
+const SUN_LIGHT_RANGE: f32 = 35.0;
+// Only planets closer than this to each other are tested for eclipses
+const ECLIPSE_RANGE: f32 = 40.0;
+// Atmosphere shells reach ATMOSPHERE_THICKNESS radii above the surface and
+// add up to ATMOSPHERE_OPACITY of their color at the limb. Across the disk
+// the haze fades in as (distance from center)^ATMOSPHERE_DISK_FALLOFF, and
+// ATMOSPHERE_WRAP lets sunlight scatter a little past the terminator
+const ATMOSPHERE_THICKNESS: f32 = 0.12;
+const ATMOSPHERE_OPACITY: f32 = 0.6;
+const ATMOSPHERE_DISK_FALLOFF: i32 = 6;
+const ATMOSPHERE_WRAP: f32 = 0.3;
+// Lava worlds light their neighbors faintly, halving by this many radii out
+const LAVA_GLOW_INTENSITY: f32 = 0.5;
+const LAVA_GLOW_RANGE: f32 = 4.0;
+// Light from stars with no particular color, and lava glow
+const WHITE_LIGHT: Color = Color { r: 255, g: 255, b: 255 };
+// `make_binary` splits the sun into a warm primary and a smaller blue
+// companion BINARY_SEPARATION apart, well inside Terra's orbit at r=20
+const BINARY_SEPARATION: f32 = 10.0;
+const BINARY_ORBIT_SPEED: f32 = 1.0;
+const BINARY_PRIMARY_SCALE: f32 = 3.5;
+const BINARY_COMPANION_SCALE: f32 = 2.5;
+const BINARY_PRIMARY_LIGHT: Color = Color { r: 255, g: 205, b: 150 };
+const BINARY_COMPANION_LIGHT: Color = Color { r: 160, g: 190, b: 255 };
+// Asteroids are tiny, so they're skipped well before the fog would hide them
+const ASTEROID_DRAW_DISTANCE: f32 = 70.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    pub fn from_float(r: f32, g: f32, b: f32) -> Self {
+        Color {
+            r: (r.clamp(0.0, 1.0) * 255.0) as u8,
+            g: (g.clamp(0.0, 1.0) * 255.0) as u8,
+            b: (b.clamp(0.0, 1.0) * 255.0) as u8,
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    }
+
+    pub fn mul(&self, factor: f32) -> Color {
+        Color::from_float(
+            self.r as f32 / 255.0 * factor,
+            self.g as f32 / 255.0 * factor,
+            self.b as f32 / 255.0 * factor,
+        )
+    }
+
+    /// Each channel scaled by the matching one of `light`, as this surface
+    /// would look under that colored light.
+    pub fn filter(&self, light: &Color) -> Color {
+        let channel = |c: u8, l: u8| (c as u16 * l as u16 / 255) as u8;
+        Color::new(channel(self.r, light.r), channel(self.g, light.g), channel(self.b, light.b))
+    }
+
+    pub fn lerp(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        Color::from_float(
+            (self.r as f32 / 255.0) * (1.0 - t) + (other.r as f32 / 255.0) * t,
+            (self.g as f32 / 255.0) * (1.0 - t) + (other.g as f32 / 255.0) * t,
+            (self.b as f32 / 255.0) * (1.0 - t) + (other.b as f32 / 255.0) * t,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub fn dot(&self, other: &Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vec3 {
+        let len = self.length();
+        if len > 0.0001 {
+            Vec3::new(self.x / len, self.y / len, self.z / len)
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        }
+    }
+
+    pub fn add(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+
+    pub fn sub(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+
+    pub fn mul(&self, scalar: f32) -> Vec3 {
+        Vec3::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+
+    pub fn rotate_y(&self, angle: f32) -> Vec3 {
+        let cos_a = angle.cos();
+        let sin_a = angle.sin();
+        Vec3::new(
+            self.x * cos_a + self.z * sin_a,
+            self.y,
+            -self.x * sin_a + self.z * cos_a,
+        )
+    }
+
+    pub fn rotate_z(&self, angle: f32) -> Vec3 {
+        let cos_a = angle.cos();
+        let sin_a = angle.sin();
+        Vec3::new(
+            self.x * cos_a - self.y * sin_a,
+            self.x * sin_a + self.y * cos_a,
+            self.z,
+        )
+    }
+}
+
+// The operators take Vec3 by value (it's Copy), so `a + b * s` works without
+// the borrows the named methods need
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::add(&self, &other)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::sub(&self, &other)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scalar: f32) -> Vec3 {
+        Vec3::mul(&self, scalar)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Pose {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+pub struct Spaceship {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    pub target_roll: f32,
+    /// Pitch to ease toward, set while leveling out and cleared on arrival
+    pub target_pitch: Option<f32>,
+    pub model: Option<Model>,
+    pub warping: bool,
+    pub warp_progress: f32,
+    warp_start: Pose,
+    warp_target: Pose,
+    warp_anchor: Option<(usize, Vec3)>,
+    pub health: f32,
+    pub fuel: f32,
+    pub heat: f32,
+    pub boost_locked: bool,
+    /// Bleeds off speed every step. Off, the ship coasts until thrust or
+    /// gravity changes its course, and may fly up to `NEWTONIAN_MAX_SPEED`.
+    pub inertial_dampeners: bool,
+    /// Slows the ship's approach to the nearest planet so it touches down
+    /// below the crash threshold instead of slamming into it.
+    pub auto_brake: bool,
+    /// Forward speed cruise control holds, while engaged.
+    pub cruise_speed: Option<f32>,
+    /// Indices of the planets the ship has flown close to
+    pub explored: HashSet<usize>,
+    /// Distance flown under the ship's own power; warps don't count
+    pub odometer: f32,
+    encounter: Option<Encounter>,
+    /// Planet index and speed gained by the latest gravity assist, for the
+    /// driver to take and announce
+    pub last_slingshot: Option<(usize, f32)>,
+    /// Speed of the latest collision hard enough to do damage, for the
+    /// driver to take and react to
+    pub last_impact: Option<f32>,
+}
+
+/// A pass near a planet in progress, remembered until the ship leaves
+/// `SLINGSHOT_RANGE` so the way out can be compared with the way in.
+#[derive(Clone, Copy)]
+struct Encounter {
+    planet: usize,
+    /// Velocity relative to the planet on the way in
+    entry_velocity: Vec3,
+    /// Speed relative to the sun on the way in
+    entry_speed: f32,
+    closest_gap: f32,
+}
+
+/// Where the ship starts, and where it respawns after being destroyed.
+pub const SPAWN_POSE: Pose = Pose {
+    position: Vec3::new(0.0, 5.0, 25.0),
+    yaw: 0.0,
+    pitch: 0.0,
+};
+
+impl Default for Spaceship {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spaceship {
+    pub fn new() -> Self {
+        Spaceship {
+            position: SPAWN_POSE.position,
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            target_roll: 0.0,
+            target_pitch: None,
+            model: None,
+            warping: false,
+            warp_progress: 0.0,
+            warp_start: SPAWN_POSE,
+            warp_target: SPAWN_POSE,
+            warp_anchor: None,
+            health: MAX_HEALTH,
+            fuel: MAX_FUEL,
+            heat: 0.0,
+            boost_locked: false,
+            inertial_dampeners: true,
+            auto_brake: true,
+            cruise_speed: None,
+            explored: HashSet::new(),
+            odometer: 0.0,
+            encounter: None,
+            last_slingshot: None,
+            last_impact: None,
+        }
+    }
+
+    pub fn load_model(path: &str) -> Option<Model> {
+        match Model::load_from_file(path) {
+            Ok(mut model) => {
+                model.triangulate();
+                model.fit_to_size(SHIP_MODEL_SIZE);
+                Some(model)
+            }
+            Err(err) => {
+                eprintln!("Could not load ship model '{}' ({}), using built-in X-Wing", path, err);
+                None
+            }
+        }
+    }
+
+    /// Ship space to world space.
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::translation(self.position) * Mat4::rotation(self.yaw, self.pitch, self.roll)
+    }
+
+    pub fn to_world(&self, local: &Vec3) -> Vec3 {
+        self.model_matrix().transform_point(local)
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        )
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        let forward = self.get_forward();
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        forward.cross(&up).normalize()
+    }
+
+    pub fn update(&mut self, dt: f32, system: &SolarSystem, boosting: bool) {
+        let planets = &system.planets;
+        self.fuel = (self.fuel + FUEL_REGEN_RATE * dt).min(MAX_FUEL);
+        self.update_heat(dt, boosting);
+        if self.warping {
+            self.update_warp(dt, planets);
+            return;
+        }
+
+        if let Some(speed) = self.cruise_speed {
+            self.hold_cruise_speed(speed);
+        }
+        self.velocity = self.velocity + gravity_acceleration(&self.position, planets, &system.grid) * dt;
+        if self.auto_brake {
+            self.apply_auto_brake(system);
+        }
+        let new_position = self.position + self.velocity * dt;
+        
+        match check_collision(&new_position, planets, &system.grid) {
+            None => {
+                self.odometer += self.velocity.length() * dt;
+                self.position = new_position;
+                self.record_visits(system);
+                self.track_encounter(system);
+            }
+            Some(collision) => {
+                let impact_speed = self.velocity.length();
+                let severity = ((impact_speed - SAFE_IMPACT_SPEED) / (MAX_SPEED - SAFE_IMPACT_SPEED)).max(0.0);
+                self.health -= CRASH_DAMAGE * severity * severity;
+                if impact_speed > SAFE_IMPACT_SPEED {
+                    self.last_impact = Some(impact_speed);
+                }
+
+                // Scraping the surface is a crash, not a flyby
+                self.encounter = None;
+
+                // Reflect the inward part of the velocity, losing some energy
+                let n = collision.normal;
+                let approach = self.velocity.dot(&n);
+                if approach < 0.0 {
+                    self.velocity = self.velocity - n * ((1.0 + RESTITUTION) * approach);
+                }
+                // Planets keep orbiting, so push the ship back out to the surface
+                let planet = &planets[collision.planet];
+                self.position = planet.position + n * (planet.scale + SHIP_CLEARANCE);
+
+                if self.health <= 0.0 {
+                    self.health = MAX_HEALTH;
+                    self.warp_to(SPAWN_POSE.position, SPAWN_POSE.yaw, SPAWN_POSE.pitch);
+                    return;
+                }
+            }
+        }
+        
+        if self.inertial_dampeners {
+            self.velocity = self.velocity * DAMPING;
+        } else {
+            self.limit_speed();
+        }
+        self.roll += (self.target_roll - self.roll) * 5.0 * dt;
+        if let Some(target) = self.target_pitch {
+            self.pitch += (target - self.pitch) * (LEVEL_RATE * dt).min(1.0);
+            if (target - self.pitch).abs() < 0.001 {
+                self.pitch = target;
+                self.target_pitch = None;
+            }
+        }
+    }
+
+    /// Where the ship would coast over the next `steps` steps of `dt` under
+    /// gravity and damping, starting with its current position. Planets are
+    /// held where they are now, and the path stops at the first one it hits.
+    pub fn predict_trajectory(&self, system: &SolarSystem, steps: usize, dt: f32) -> Vec<Vec3> {
+        let (mut position, mut velocity) = (self.position, self.velocity);
+        let mut path = vec![position];
+        for _ in 0..steps {
+            velocity = velocity + gravity_acceleration(&position, &system.planets, &system.grid) * dt;
+            position = position + velocity * dt;
+            if check_collision(&position, &system.planets, &system.grid).is_some() {
+                break;
+            }
+            path.push(position);
+            if self.inertial_dampeners {
+                velocity = velocity * DAMPING;
+            } else if velocity.length() > self.max_speed() {
+                velocity = velocity * (self.max_speed() / velocity.length());
+            }
+        }
+        path
+    }
+
+    /// Starts easing pitch and roll back to level, keeping the heading.
+    pub fn level_out(&mut self) {
+        self.target_pitch = Some(0.0);
+        self.target_roll = 0.0;
+    }
+
+    pub fn update_heat(&mut self, dt: f32, boosting: bool) {
+        if boosting {
+            self.heat = (self.heat + BOOST_HEAT_RATE * dt).min(1.0);
+            if self.heat >= 1.0 {
+                self.boost_locked = true;
+            }
+        } else {
+            self.heat = (self.heat - BOOST_COOL_RATE * dt).max(0.0);
+            if self.heat <= BOOST_UNLOCK_HEAT {
+                self.boost_locked = false;
+            }
+        }
+    }
+
+    pub fn update_warp(&mut self, dt: f32, planets: &[Planet]) {
+        self.warp_progress = (self.warp_progress + dt / WARP_DURATION).min(1.0);
+        let t = self.warp_progress;
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        // Shift the destination along with the planet it was computed from
+        let mut target = self.warp_target.position;
+        if let Some((index, anchor_start)) = self.warp_anchor {
+            if let Some(planet) = planets.get(index) {
+                target = target + (planet.position - anchor_start);
+            }
+        }
+
+        let start = self.warp_start;
+        self.position = start.position + (target - start.position) * eased;
+        self.yaw = start.yaw + angle_difference(self.warp_target.yaw, start.yaw) * eased;
+        self.pitch = start.pitch + (self.warp_target.pitch - start.pitch) * eased;
+        self.roll *= 1.0 - eased;
+
+        if self.warp_progress >= 1.0 {
+            self.warping = false;
+            self.warp_anchor = None;
+        }
+    }
+
+    /// Marks every planet the ship is now close enough to as explored.
+    fn record_visits(&mut self, system: &SolarSystem) {
+        let reach = system.grid.max_radius() * EXPLORE_RADIUS_FACTOR;
+        for i in system.grid.nearby(&self.position, reach) {
+            let planet = &system.planets[i];
+            if (self.position - planet.position).length() < planet.scale * EXPLORE_RADIUS_FACTOR {
+                self.explored.insert(i);
+            }
+        }
+    }
+
+    /// Follows close passes by the nearest orbiting planet and judges each
+    /// one as the ship leaves it.
+    fn track_encounter(&mut self, system: &SolarSystem) {
+        let index = system.nearest_planet_index(&self.position);
+        let planet = &system.planets[index];
+        let gap = surface_gap(&self.position, planet);
+        if let Some(encounter) = &mut self.encounter {
+            if encounter.planet == index && gap < SLINGSHOT_RANGE {
+                encounter.closest_gap = encounter.closest_gap.min(gap);
+                return;
+            }
+        }
+        if let Some(encounter) = self.encounter.take() {
+            self.finish_encounter(encounter, &system.planets[encounter.planet]);
+        }
+        // The sun sits still, so it has no orbital motion to lend
+        if gap < SLINGSHOT_RANGE && planet.orbit_radius > 0.0 {
+            self.encounter = Some(Encounter {
+                planet: index,
+                entry_velocity: self.velocity - planet.velocity,
+                entry_speed: self.velocity.length(),
+                closest_gap: gap,
+            });
+        }
+    }
+
+    /// Grants a gravity assist for a close, course-bending pass. In the
+    /// planet's frame a real flyby only turns the ship, keeping its speed;
+    /// seen from the sun that turn carries off some of the planet's orbital
+    /// motion. Gravity does the turning here, but damping and the planet's
+    /// own motion mean it rarely delivers the full gain, so whatever is
+    /// missing, up to `SLINGSHOT_MAX_GAIN`, gets added along the new course.
+    fn finish_encounter(&mut self, encounter: Encounter, planet: &Planet) {
+        let exit_velocity = self.velocity - planet.velocity;
+        let (entry_relative, exit_relative) = (encounter.entry_velocity.length(), exit_velocity.length());
+        let speed = self.velocity.length();
+        if encounter.closest_gap > SLINGSHOT_CLOSE_PASS || entry_relative.min(exit_relative).min(speed) < f32::EPSILON {
+            return;
+        }
+        let turn = encounter.entry_velocity.dot(&exit_velocity) / (entry_relative * exit_relative);
+        if turn > SLINGSHOT_MIN_TURN.cos() {
+            return;
+        }
+
+        let ideal_exit = planet.velocity + exit_velocity * (entry_relative / exit_relative);
+        let expected_gain = (ideal_exit.length() - encounter.entry_speed).min(SLINGSHOT_MAX_GAIN);
+        // Passing ahead of a planet would slow the ship; that's left to gravity
+        if expected_gain <= 0.0 {
+            return;
+        }
+        let bonus = (expected_gain - (speed - encounter.entry_speed)).min(expected_gain);
+        if bonus <= 0.0 {
+            return;
+        }
+        self.velocity = self.velocity * ((speed + bonus) / speed);
+        self.limit_speed();
+        // Already at the speed cap there's nothing left to gain
+        let gained = self.velocity.length() - speed;
+        if gained > 0.01 {
+            self.last_slingshot = Some((encounter.planet, gained));
+        }
+    }
+
+    /// Engages cruise control at the current forward speed, or releases it.
+    pub fn toggle_cruise(&mut self) {
+        self.cruise_speed = match self.cruise_speed {
+            Some(_) => None,
+            None => Some(self.velocity.dot(&self.get_forward()).max(0.0)),
+        };
+    }
+
+    /// Thrusts along the nose just enough that the forward speed comes out
+    /// at `speed` once this step's damping has been applied.
+    fn hold_cruise_speed(&mut self, speed: f32) {
+        let target = if self.inertial_dampeners { speed / DAMPING } else { speed };
+        let thrust = target - self.velocity.dot(&self.get_forward());
+        if thrust.abs() > f32::EPSILON {
+            self.accelerate(self.get_forward(), thrust.clamp(-CRUISE_THRUST, CRUISE_THRUST));
+        }
+    }
+
+    /// Scales the velocity down so the speed toward the nearest planet falls
+    /// from the cap to a safe landing speed over the auto-brake distance.
+    fn apply_auto_brake(&mut self, system: &SolarSystem) {
+        let planet = system.nearest_planet(&self.position);
+        let gap = surface_gap(&self.position, planet);
+        if gap >= AUTO_BRAKE_DISTANCE {
+            return;
+        }
+        let inward = self.velocity.dot(&(planet.position - self.position).normalize());
+        let allowed = SAFE_IMPACT_SPEED + (MAX_SPEED - SAFE_IMPACT_SPEED) * (gap / AUTO_BRAKE_DISTANCE).max(0.0);
+        if inward > allowed {
+            self.velocity = self.velocity * (allowed / inward);
+        }
+    }
+
+    /// True while the nearest planet is close and closing faster than a safe
+    /// impact, counting its orbital motion as well as the ship's.
+    pub fn proximity_warning(&self, system: &SolarSystem) -> bool {
+        let planet = system.nearest_planet(&self.position);
+        if self.warping || surface_gap(&self.position, planet) >= PROXIMITY_WARNING_DISTANCE {
+            return false;
+        }
+        let closing = (self.velocity - planet.velocity).dot(&(planet.position - self.position).normalize());
+        closing > SAFE_IMPACT_SPEED
+    }
+
+    /// Thrusts along `direction`, or does nothing when the tank is dry.
+    pub fn accelerate(&mut self, direction: Vec3, speed: f32) {
+        if !self.spend_fuel(speed.abs() * FUEL_PER_THRUST) {
+            return;
+        }
+        self.velocity = self.velocity + direction * speed;
+        self.limit_speed();
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        if self.inertial_dampeners {
+            MAX_SPEED
+        } else {
+            NEWTONIAN_MAX_SPEED
+        }
+    }
+
+    fn limit_speed(&mut self) {
+        let vel_len = self.velocity.length();
+        if vel_len > self.max_speed() {
+            self.velocity = self.velocity * (self.max_speed() / vel_len);
+        }
+    }
+
+    /// One autopilot step toward `planets[target]`: turn toward a meeting
+    /// point on its orbit, thrust while lined up, slow down on the approach
+    /// and sidestep planets in the way. Returns true once the ship has arrived.
+    pub fn steer_autopilot(&mut self, target: usize, planets: &[Planet], dt: f32) -> bool {
+        let Some(planet) = planets.get(target) else {
+            return true;
+        };
+        let gap = (planet.position - self.position).length() - planet.scale - SHIP_CLEARANCE;
+        if gap < AUTOPILOT_ARRIVAL_DISTANCE {
+            self.target_roll = 0.0;
+            return true;
+        }
+
+        // Planets outrun the ship, so rather than chasing the target, head
+        // for the first point on its orbit the ship can reach in time and
+        // wait there. That spot sits above the ecliptic, where no orbiting
+        // planet can sweep through the ship.
+        let above = Vec3::new(0.0, AUTOPILOT_CRUISE_ALTITUDE.copysign(self.position.y), 0.0);
+        let aim = (0..=AUTOPILOT_INTERCEPT_STEPS)
+            .map(|step| step as f32 * AUTOPILOT_INTERCEPT_STEP)
+            .map(|seconds| (seconds, planet.position_after(seconds) + above))
+            .find(|(seconds, point)| (*point - self.position).length() <= AUTOPILOT_CRUISE_SPEED * seconds)
+            .map_or(planet.position + above, |(_, point)| point);
+        let to_aim = aim - self.position;
+
+        // Climb to the waiting altitude early instead of drifting up to it
+        // on the way, which would skim the sun and any orbits in between
+        let level = Vec3::new(to_aim.x, 0.0, to_aim.z).normalize();
+        let direction = (level + Vec3::new(0.0, to_aim.y * AUTOPILOT_CLIMB_RATE, 0.0)).normalize();
+        let desired_yaw = direction.x.atan2(direction.z);
+        let desired_pitch = direction.y.asin().clamp(-PI / 3.0, PI / 3.0);
+        // Same turn rate as the arrow keys, banking into the turn the same way
+        let turn_rate = 2.0 * dt;
+        let yaw_step = angle_difference(desired_yaw, self.yaw).clamp(-turn_rate, turn_rate);
+        self.yaw += yaw_step;
+        self.pitch += (desired_pitch - self.pitch).clamp(-turn_rate, turn_rate);
+        self.target_roll = 0.4 * yaw_step / turn_rate.max(f32::EPSILON);
+
+        let speed = self.velocity.length();
+        let desired_speed = (to_aim.length() * AUTOPILOT_APPROACH_RATE).min(MAX_SPEED);
+        // Look for a planet the current course meets within the lookahead,
+        // checked in each planet's moving frame since they orbit faster than
+        // the ship can fly
+        let threat = planets.iter().find_map(|p| {
+            (1..=AUTOPILOT_LOOKAHEAD_SAMPLES).find_map(|i| {
+                let seconds = AUTOPILOT_LOOKAHEAD * i as f32 / AUTOPILOT_LOOKAHEAD_SAMPLES as f32;
+                let drift = p.position_after(seconds) - p.position;
+                let closing = drift - self.velocity * seconds;
+                planet_collision(&(self.position - closing), 0, p).map(|c| (c.normal, closing))
+            })
+        });
+        if let Some((normal, closing)) = threat {
+            // Sidestep across the planet's path rather than trying to outrun it
+            let closing = closing.normalize();
+            let sidestep = normal - closing * normal.dot(&closing);
+            let dodge = if sidestep.length() > 0.1 { sidestep.normalize() } else { normal };
+            self.accelerate(dodge, AUTOPILOT_THRUST);
+        } else if speed > desired_speed {
+            if speed > 0.01 {
+                self.accelerate(self.velocity * (1.0 / speed), -AUTOPILOT_THRUST.min(speed));
+            }
+        } else if self.get_forward().dot(&direction) > 0.95 {
+            self.accelerate(self.get_forward(), AUTOPILOT_THRUST);
+        }
+        false
+    }
+
+    /// Burns `amount` of fuel if the tank holds that much.
+    pub fn spend_fuel(&mut self, amount: f32) -> bool {
+        if self.fuel < amount {
+            return false;
+        }
+        self.fuel -= amount;
+        true
+    }
+
+    /// Starts an animated warp; `update` carries the ship there over
+    /// `WARP_DURATION` seconds.
+    pub fn warp_to(&mut self, target: Vec3, target_yaw: f32, target_pitch: f32) {
+        self.warp_start = Pose {
+            position: self.position,
+            yaw: self.yaw,
+            pitch: self.pitch,
+        };
+        self.warp_target = Pose {
+            position: target,
+            yaw: target_yaw,
+            pitch: target_pitch,
+        };
+        self.warp_progress = 0.0;
+        self.warp_anchor = None;
+        self.warping = true;
+        self.velocity = Vec3::new(0.0, 0.0, 0.0);
+        self.target_roll = 0.0;
+        self.target_pitch = None;
+        self.cruise_speed = None;
+        self.encounter = None;
+    }
+
+    /// Warps to a viewpoint just outside `planet`'s orbit, looking back
+    /// past it toward the sun. The standoff grows with the planet's size.
+    pub fn warp_to_planet(&mut self, planet: &Planet) {
+        let distance = planet.scale * 2.0 + 4.0;
+        let angle = planet.orbit_angle;
+        let target = Vec3::new(
+            planet.position.x + distance * angle.cos(),
+            planet.position.y + distance * 0.6,
+            planet.position.z + distance * angle.sin(),
+        );
+        // Facing back along the offset, so forward is (-cos a, -sin a) in x/z
+        let yaw = (-angle.cos()).atan2(-angle.sin());
+        self.warp_to(target, yaw, -0.15);
+    }
+
+    /// Makes the current warp track `planets[index]` as it keeps orbiting.
+    pub fn anchor_warp(&mut self, index: usize, planets: &[Planet]) {
+        self.warp_anchor = planets.get(index).map(|p| (index, p.position));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    Chase,
+    Free,
+    Cockpit,
+}
+
+pub struct Camera {
+    pub mode: CameraMode,
+    pub distance: f32,
+    pub height: f32,
+    smoothed_position: Vec3,
+    smoothed_yaw: f32,
+    smoothed_pitch: f32,
+    free_position: Vec3,
+    free_yaw: f32,
+    free_pitch: f32,
+    ship_pose: Pose,
+    /// Field of view things are drawn with: the zoom plus the speed stretch
+    pub fov: f32,
+    /// Zoom level easing toward `target_fov`
+    zoom_fov: f32,
+    target_fov: f32,
+    /// Widening from the ship's speed, see `SPEED_FOV_STRETCH`
+    speed_fov: f32,
+    /// Fraction of the chase offset in use, below 1 while a planet would
+    /// otherwise swallow the camera
+    chase_reach: f32,
+    /// Size of the shake still to play out, see `shake`
+    shake: f32,
+    shake_offset: Vec3,
+    shake_rng: Rng,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            mode: CameraMode::Chase,
+            distance: 10.0,
+            height: 4.0,
+            smoothed_position: Vec3::new(0.0, 5.0, 25.0),
+            smoothed_yaw: 0.0,
+            smoothed_pitch: 0.0,
+            free_position: Vec3::new(0.0, 5.0, 25.0),
+            free_yaw: 0.0,
+            free_pitch: 0.0,
+            ship_pose: Pose {
+                position: Vec3::new(0.0, 5.0, 25.0),
+                yaw: 0.0,
+                pitch: 0.0,
+            },
+            fov: FOV,
+            zoom_fov: FOV,
+            target_fov: FOV,
+            speed_fov: 0.0,
+            chase_reach: 1.0,
+            shake: 0.0,
+            shake_offset: Vec3::new(0.0, 0.0, 0.0),
+            shake_rng: Rng::new(2024),
+        }
+    }
+
+    /// Narrows (negative `amount`) or widens the field of view the camera
+    /// eases toward.
+    pub fn zoom(&mut self, amount: f32) {
+        self.target_fov = (self.target_fov + amount).clamp(MIN_FOV, MAX_FOV);
+    }
+
+    /// Jolts the chase and cockpit views by up to `impulse` world units. The
+    /// jitter only rides on top of the view and dies away on its own, so
+    /// the smoothed follow position never drifts because of it.
+    pub fn shake(&mut self, impulse: f32) {
+        self.shake = (self.shake + impulse).min(MAX_SHAKE);
+    }
+
+    /// Magnification relative to the default FOV. Fog and distance culling
+    /// divide by it so zooming works like a spyglass on far planets. The
+    /// speed stretch is left out, so fog doesn't creep in during a boost.
+    pub fn zoom_factor(&self) -> f32 {
+        (FOV / 2.0).tan() / (self.zoom_fov / 2.0).tan()
+    }
+
+    pub fn update(&mut self, spaceship: &Spaceship, system: &SolarSystem, dt: f32) {
+        self.zoom_fov += (self.target_fov - self.zoom_fov) * (8.0 * dt).min(1.0);
+
+        // The stretch follows the ship, so the free camera leaves it behind.
+        // It's relative to the zoom, otherwise it would swamp a narrow spyglass
+        let target_stretch = if self.mode == CameraMode::Free {
+            0.0
+        } else {
+            let ratio = (spaceship.velocity.length() / spaceship.max_speed()).min(1.0);
+            SPEED_FOV_STRETCH * ratio * ratio * self.zoom_fov / FOV
+        };
+        self.speed_fov += (target_stretch - self.speed_fov) * (SPEED_FOV_RATE * dt).min(1.0);
+        self.fov = self.zoom_fov + self.speed_fov;
+
+        self.shake *= (-SHAKE_DECAY * dt).exp();
+        let mut jitter = || self.shake_rng.range(-1.0, 1.0);
+        self.shake_offset = Vec3::new(jitter(), jitter(), jitter()) * self.shake;
+
+        // Chase smoothing keeps running in free mode so switching back is seamless
+        let smooth_factor = 5.0 * dt;
+        
+        self.smoothed_position = Vec3::new(
+            self.smoothed_position.x + (spaceship.position.x - self.smoothed_position.x) * smooth_factor,
+            self.smoothed_position.y + (spaceship.position.y - self.smoothed_position.y) * smooth_factor,
+            self.smoothed_position.z + (spaceship.position.z - self.smoothed_position.z) * smooth_factor,
+        );
+        
+        self.smoothed_yaw += angle_difference(spaceship.yaw, self.smoothed_yaw) * smooth_factor;
+        self.smoothed_pitch += (spaceship.pitch - self.smoothed_pitch) * smooth_factor;
+
+        // The cockpit view is rigidly attached to the ship, no smoothing
+        self.ship_pose = Pose {
+            position: spaceship.position,
+            yaw: spaceship.yaw,
+            pitch: spaceship.pitch,
+        };
+
+        // Slide in toward the ship along the view axis until clear of every
+        // planet. Pull in at once, but ease back out so the view doesn't pop
+        let offset = self.chase_offset(1.0);
+        let clear = (0..=CAMERA_PULL_STEPS)
+            .map(|i| 1.0 - (1.0 - CAMERA_MIN_REACH) * i as f32 / CAMERA_PULL_STEPS as f32)
+            .find(|&reach| {
+                let position = self.smoothed_position + offset * reach;
+                check_collision(&position, &system.planets, &system.grid).is_none()
+            })
+            .unwrap_or(CAMERA_MIN_REACH);
+        self.chase_reach = if clear < self.chase_reach {
+            clear
+        } else {
+            self.chase_reach + (clear - self.chase_reach) * smooth_factor.min(1.0)
+        };
+    }
+
+    /// Chase camera position relative to the ship it trails, scaled by `reach`.
+    fn chase_offset(&self, reach: f32) -> Vec3 {
+        Vec3::new(
+            -self.smoothed_yaw.sin() * self.smoothed_pitch.cos() * self.distance,
+            self.height - self.smoothed_pitch.sin() * self.distance * 0.5,
+            -self.smoothed_yaw.cos() * self.smoothed_pitch.cos() * self.distance,
+        ) * reach
+    }
+
+    /// Drops any smoothing lag and zoom and goes back to the chase view, so
+    /// a long jump doesn't make the camera swing around to catch up.
+    pub fn reset(&mut self, spaceship: &Spaceship) {
+        self.mode = CameraMode::Chase;
+        self.snap_behind(spaceship);
+        self.fov = FOV;
+        self.zoom_fov = FOV;
+        self.target_fov = FOV;
+        self.speed_fov = 0.0;
+    }
+
+    /// Catches the chase smoothing up to the ship at once, leaving the mode
+    /// and zoom alone.
+    pub fn snap_behind(&mut self, spaceship: &Spaceship) {
+        self.smoothed_position = spaceship.position;
+        self.smoothed_yaw = spaceship.yaw;
+        self.smoothed_pitch = spaceship.pitch;
+    }
+
+    pub fn cycle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Chase => {
+                // Start flying from wherever the chase camera currently is
+                let forward = self.get_forward();
+                self.free_position = self.steady_position();
+                self.free_yaw = forward.x.atan2(forward.z);
+                self.free_pitch = forward.y.clamp(-1.0, 1.0).asin();
+                CameraMode::Free
+            }
+            CameraMode::Free => CameraMode::Cockpit,
+            CameraMode::Cockpit => CameraMode::Chase,
+        };
+    }
+
+    /// Moves the free camera by `movement` (x = right, y = up, z = forward)
+    /// in its own frame and turns it by the given yaw/pitch deltas.
+    pub fn fly(&mut self, movement: Vec3, yaw_delta: f32, pitch_delta: f32) {
+        self.free_yaw += yaw_delta;
+        self.free_pitch = (self.free_pitch + pitch_delta).clamp(-PI / 2.0 + 0.01, PI / 2.0 - 0.01);
+
+        let forward = self.get_forward();
+        let right = self.get_right();
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        self.free_position = self.free_position + right * movement.x + up * movement.y + forward * movement.z;
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Free => self.free_position,
+            _ => self.steady_position() + self.shake_offset,
+        }
+    }
+
+    /// Where the chase or cockpit eye would be without any shake. The view
+    /// direction comes from here, so a shake moves the eye but never swings
+    /// the view, even with the chase camera pulled in close.
+    fn steady_position(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Chase => self.smoothed_position + self.chase_offset(self.chase_reach),
+            CameraMode::Free => self.free_position,
+            CameraMode::Cockpit => self.ship_pose.position + self.get_forward() * COCKPIT_OFFSET,
+        }
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Chase => (self.smoothed_position - self.steady_position()).normalize(),
+            CameraMode::Free => Vec3::new(
+                self.free_yaw.sin() * self.free_pitch.cos(),
+                self.free_pitch.sin(),
+                self.free_yaw.cos() * self.free_pitch.cos(),
+            ),
+            CameraMode::Cockpit => Vec3::new(
+                self.ship_pose.yaw.sin() * self.ship_pose.pitch.cos(),
+                self.ship_pose.pitch.sin(),
+                self.ship_pose.yaw.cos() * self.ship_pose.pitch.cos(),
+            ),
+        }
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        let forward = self.get_forward();
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        forward.cross(&up).normalize()
+    }
+
+    pub fn projection(&self, width: usize, height: usize) -> Mat4 {
+        Mat4::perspective(self.fov, width as f32 / height as f32, NEAR_PLANE, FAR_PLANE)
+    }
+
+    /// World to clip space for a `width`×`height` target. Renderers build it
+    /// once per call and reuse it for every vertex.
+    pub fn view_projection(&self, width: usize, height: usize) -> Mat4 {
+        let eye = self.get_position();
+        let view = Mat4::look_at(&eye, &(eye + self.get_forward()), &Vec3::new(0.0, 1.0, 0.0));
+        self.projection(width, height) * view
+    }
+}
+
+// Nozzle positions in ship space, at the back of the four X-Wing nacelles
+const ENGINE_MOUNTS: [Vec3; 4] = [
+    Vec3::new(-1.4, 0.75, -1.1),
+    Vec3::new(1.4, 0.75, -1.1),
+    Vec3::new(-1.4, -0.75, -1.1),
+    Vec3::new(1.4, -0.75, -1.1),
+];
+
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    life: f32,
+    max_life: f32,
+    brightness: f32,
+}
+
+pub struct ExhaustTrail {
+    particles: Vec<Particle>,
+    rng: Rng,
+}
+
+impl Default for ExhaustTrail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExhaustTrail {
+    pub fn new() -> Self {
+        ExhaustTrail {
+            particles: Vec::new(),
+            rng: Rng::new(54321),
+        }
+    }
+
+    pub fn emit(&mut self, spaceship: &Spaceship, boosting: bool) {
+        let backward = -spaceship.get_forward();
+        let per_engine = if boosting { 3 } else { 1 };
+
+        for mount in &ENGINE_MOUNTS {
+            let origin = spaceship.to_world(mount);
+            for _ in 0..per_engine {
+                let jitter = Vec3::new(
+                    self.rng.next_f32() - 0.5,
+                    self.rng.next_f32() - 0.5,
+                    self.rng.next_f32() - 0.5,
+                ) * 0.6;
+                let max_life = 0.35 + self.rng.next_f32() * 0.25;
+                self.particles.push(Particle {
+                    position: origin,
+                    velocity: spaceship.velocity + backward * 3.0 + jitter,
+                    life: max_life,
+                    max_life,
+                    brightness: if boosting { 1.0 } else { 0.7 },
+                });
+            }
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.position = particle.position + particle.velocity * dt;
+            particle.life -= dt;
+        }
+        self.particles.retain(|p| p.life > 0.0);
+    }
+
+    pub fn render(&self, fb: &mut Framebuffer, camera: &Camera) {
+        let view_proj = camera.view_projection(fb.width, fb.height);
+
+        let hot = Color::new(255, 220, 120);
+        let cool = Color::new(200, 40, 20);
+
+        for particle in &self.particles {
+            let projected = project_vertex(&particle.position, &view_proj, fb.width, fb.height);
+            if let Some((sx, sy, z)) = projected {
+                let t = particle.life / particle.max_life;
+                let color = cool.lerp(&hot, t).mul(particle.brightness * t.sqrt()).to_u32();
+                // Two window pixels wide regardless of supersampling
+                let size = 2 * fb.scale as i32;
+                for dy in 0..size {
+                    for dx in 0..size {
+                        fb.plot(sx as i32 + dx, sy as i32 + dy, z, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn angle_difference(target: f32, current: f32) -> f32 {
+    let mut diff = target - current;
+    while diff > PI {
+        diff -= 2.0 * PI;
+    }
+    while diff < -PI {
+        diff += 2.0 * PI;
+    }
+    diff
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShaderType {
+    Sun,
+    Earth,
+    GasGiant,
+    Ice,
+    Desert,
+    Lava,
+    Purple,
+    Ocean,
+    Toxic,
+    Crystal,
+    Moon,
+}
+
+impl ShaderType {
+    /// Rough average of the shader's palette, for places too small to shade.
+    pub fn representative_color(self) -> Color {
+        match self {
+            ShaderType::Sun => Color::new(255, 210, 140),
+            ShaderType::Earth => Color::new(50, 110, 150),
+            ShaderType::GasGiant => Color::new(200, 160, 105),
+            ShaderType::Ice => Color::new(150, 200, 245),
+            ShaderType::Desert => Color::new(200, 140, 80),
+            ShaderType::Lava => Color::new(200, 80, 40),
+            ShaderType::Purple => Color::new(125, 80, 180),
+            ShaderType::Ocean => Color::new(30, 90, 170),
+            ShaderType::Toxic => Color::new(150, 190, 40),
+            ShaderType::Crystal => Color::new(170, 220, 220),
+            ShaderType::Moon => Color::new(150, 150, 150),
+        }
+    }
+
+    /// Color of the translucent shell drawn around this kind of world, if
+    /// it has enough of an atmosphere to show one.
+    pub fn atmosphere_color(self) -> Option<Color> {
+        match self {
+            ShaderType::Earth => Some(Color::new(110, 170, 255)),
+            ShaderType::Ocean => Some(Color::new(90, 150, 255)),
+            ShaderType::GasGiant => Some(Color::new(235, 200, 150)),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ShaderType> {
+        match name {
+            "sun" => Some(ShaderType::Sun),
+            "earth" => Some(ShaderType::Earth),
+            "gas_giant" => Some(ShaderType::GasGiant),
+            "ice" => Some(ShaderType::Ice),
+            "desert" => Some(ShaderType::Desert),
+            "lava" => Some(ShaderType::Lava),
+            "purple" => Some(ShaderType::Purple),
+            "ocean" => Some(ShaderType::Ocean),
+            "toxic" => Some(ShaderType::Toxic),
+            "crystal" => Some(ShaderType::Crystal),
+            "moon" => Some(ShaderType::Moon),
+            _ => None,
+        }
+    }
+
+    /// The name `from_name` accepts for this shader.
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderType::Sun => "sun",
+            ShaderType::Earth => "earth",
+            ShaderType::GasGiant => "gas_giant",
+            ShaderType::Ice => "ice",
+            ShaderType::Desert => "desert",
+            ShaderType::Lava => "lava",
+            ShaderType::Purple => "purple",
+            ShaderType::Ocean => "ocean",
+            ShaderType::Toxic => "toxic",
+            ShaderType::Crystal => "crystal",
+            ShaderType::Moon => "moon",
+        }
+    }
+}
+
+pub struct Planet {
+    pub name: String,
+    pub position: Vec3,
+    /// Units per second. Follows the orbit in kinematic mode; in N-body mode
+    /// it's what actually moves the planet.
+    pub velocity: Vec3,
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub eccentricity: f32,
+    /// Tilt of the orbital plane about the x axis, in radians
+    pub inclination: f32,
+    pub rotation_speed: f32,
+    /// Lean of the spin axis away from the orbital plane's normal, in radians
+    pub axial_tilt: f32,
+    pub scale: f32,
+    pub shader: ShaderType,
+    pub rotation: f32,
+    pub orbit_angle: f32,
+    pub has_rings: bool,
+    pub ring_color: Color,
+    /// Replace the shader's two base colors, e.g. ocean and land for Earth
+    pub base_color_a: Option<Color>,
+    pub base_color_b: Option<Color>,
+    /// Wrapped around the surface in place of the shader's procedural
+    /// pattern; the shader still decides how it glows or is lit
+    pub texture: Option<Texture>,
+    pub moons: Vec<Moon>,
+}
+
+pub struct Moon {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub size: f32,
+    pub angle: f32,
+}
+
+impl Moon {
+    pub fn update(&mut self, dt: f32) {
+        self.angle += self.orbit_speed * dt;
+    }
+
+    /// Spin about its own axis. Moons are tidally locked: one turn per orbit,
+    /// so the same face always points at the planet.
+    pub fn self_rotation(&self) -> f32 {
+        self.angle
+    }
+    
+    pub fn get_position(&self, planet_pos: &Vec3) -> Vec3 {
+        Vec3::new(
+            planet_pos.x + self.orbit_radius * self.angle.cos(),
+            planet_pos.y,
+            planet_pos.z + self.orbit_radius * self.angle.sin(),
+        )
+    }
+}
+
+pub struct Asteroid {
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub size: f32,
+    pub angle: f32,
+    pub height: f32,
+}
+
+impl Asteroid {
+    /// Scatters `count` asteroids around the sun. The LCG seed is fixed so
+    /// the belt comes out identical on every run.
+    pub fn belt(count: usize, radius: f32, width: f32) -> Vec<Asteroid> {
+        let mut rng = Rng::new(4242);
+        (0..count)
+            .map(|_| Asteroid {
+                orbit_radius: radius + (rng.next_f32() - 0.5) * width,
+                orbit_speed: rng.range(0.11, 0.15),
+                size: rng.range(0.15, 0.45),
+                angle: rng.next_f32() * 2.0 * PI,
+                height: (rng.next_f32() - 0.5) * 1.5,
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.angle += self.orbit_speed * dt;
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        Vec3::new(
+            self.orbit_radius * self.angle.cos(),
+            self.height,
+            self.orbit_radius * self.angle.sin(),
+        )
+    }
+}
+
+impl Planet {
+    pub fn builder() -> PlanetBuilder {
+        PlanetBuilder {
+            planet: Planet {
+                name: String::new(),
+                position: Vec3::new(0.0, 0.0, 0.0),
+                velocity: Vec3::new(0.0, 0.0, 0.0),
+                orbit_radius: 0.0,
+                orbit_speed: 0.0,
+                eccentricity: 0.0,
+                inclination: 0.0,
+                rotation_speed: 0.0,
+                axial_tilt: 0.0,
+                scale: 1.0,
+                shader: ShaderType::Moon,
+                rotation: 0.0,
+                orbit_angle: 0.0,
+                has_rings: false,
+                ring_color: Color::new(0, 0, 0),
+                base_color_a: None,
+                base_color_b: None,
+                texture: None,
+                moons: vec![],
+            },
+        }
+    }
+
+    /// The minimap dot color, following any texture or base color overrides.
+    pub fn representative_color(&self) -> Color {
+        surface_color(self.shader, (self.base_color_a, self.base_color_b), self.texture.as_ref())
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.orbit_angle += self.angular_speed() * dt;
+        self.position = orbit_position(self.orbit_radius, self.eccentricity, self.inclination, self.orbit_angle);
+        self.velocity = self.orbit_tangent() * self.angular_speed();
+        self.update_spin(dt);
+    }
+
+    /// Spin and moons, the part of `update` that doesn't move the planet.
+    pub fn update_spin(&mut self, dt: f32) {
+        self.rotation += self.rotation_speed * dt;
+        for moon in &mut self.moons {
+            moon.update(dt);
+        }
+    }
+
+    fn angular_speed(&self) -> f32 {
+        // Kepler's second law: angular speed grows as (1 + e·cosθ)² toward perihelion
+        let e = self.eccentricity;
+        self.orbit_speed * (1.0 + e * self.orbit_angle.cos()).powi(2) / (1.0 - e * e).powf(1.5)
+    }
+
+    /// How the position changes per radian of `orbit_angle`, by central
+    /// difference.
+    fn orbit_tangent(&self) -> Vec3 {
+        let at = |angle: f32| orbit_position(self.orbit_radius, self.eccentricity, self.inclination, angle);
+        let h = ORBIT_TANGENT_STEP;
+        (at(self.orbit_angle + h) - at(self.orbit_angle - h)) * (0.5 / h)
+    }
+
+    /// Points `orbit_angle` back at where the planet really is relative to
+    /// `center`, for when N-body gravity has moved it.
+    fn sync_orbit_angle(&mut self, center: Vec3) {
+        let offset = self.position - center;
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let in_plane_z = offset.z * cos_i - offset.y * sin_i;
+        self.orbit_angle = in_plane_z.atan2(offset.x);
+    }
+
+    /// Mass for N-body gravity: its volume, with suns far denser than
+    /// planets so they still hold the system together.
+    pub fn mass(&self) -> f32 {
+        let volume = self.scale.powi(3);
+        if self.shader == ShaderType::Sun {
+            volume * SUN_DENSITY
+        } else {
+            volume
+        }
+    }
+
+    /// World-space direction of the north pole: straight up tipped by
+    /// `axial_tilt`, the inverse of the tilt `render_sphere` undoes.
+    pub fn spin_axis(&self) -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0).rotate_z(self.axial_tilt)
+    }
+
+    /// Roughly where the planet will be `seconds` from now, advancing the
+    /// orbit at its mean angular speed. Close enough for aiming.
+    pub fn position_after(&self, seconds: f32) -> Vec3 {
+        let angle = self.orbit_angle + self.orbit_speed * seconds;
+        orbit_position(self.orbit_radius, self.eccentricity, self.inclination, angle)
+    }
+}
+
+/// Chained setup for a `Planet`. Anything left unset gets a neutral
+/// default: a small gray rock sitting still at the center, with no rings,
+/// moons or tilt, named after its shader.
+pub struct PlanetBuilder {
+    planet: Planet,
+}
+
+impl PlanetBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.planet.name = name.to_string();
+        self
+    }
+
+    pub fn orbit(mut self, radius: f32, speed: f32) -> Self {
+        self.planet.orbit_radius = radius;
+        self.planet.orbit_speed = speed;
+        self
+    }
+
+    pub fn eccentricity(mut self, eccentricity: f32) -> Self {
+        self.planet.eccentricity = eccentricity;
+        self
+    }
+
+    pub fn inclination(mut self, inclination: f32) -> Self {
+        self.planet.inclination = inclination;
+        self
+    }
+
+    /// Where along the orbit the planet starts, in radians.
+    pub fn orbit_angle(mut self, angle: f32) -> Self {
+        self.planet.orbit_angle = angle;
+        self
+    }
+
+    pub fn spin(mut self, rotation_speed: f32, axial_tilt: f32) -> Self {
+        self.planet.rotation_speed = rotation_speed;
+        self.planet.axial_tilt = axial_tilt;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.planet.rotation = rotation;
+        self
+    }
+
+    pub fn shader(mut self, shader: ShaderType) -> Self {
+        self.planet.shader = shader;
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.planet.scale = scale;
+        self
+    }
+
+    pub fn base_colors(mut self, a: Option<Color>, b: Option<Color>) -> Self {
+        self.planet.base_color_a = a;
+        self.planet.base_color_b = b;
+        self
+    }
+
+    pub fn texture(mut self, texture: Texture) -> Self {
+        self.planet.texture = Some(texture);
+        self
+    }
+
+    pub fn with_ring(mut self, color: Color) -> Self {
+        self.planet.has_rings = true;
+        self.planet.ring_color = color;
+        self
+    }
+
+    pub fn add_moon(mut self, moon: Moon) -> Self {
+        self.planet.moons.push(moon);
+        self
+    }
+
+    /// Places the planet at its starting orbit angle.
+    pub fn build(self) -> Planet {
+        let mut planet = self.planet;
+        if planet.name.is_empty() {
+            planet.name = planet.shader.name().to_string();
+        }
+        planet.update(0.0);
+        planet
+    }
+}
+
+/// One color standing in for a whole surface, for when it's too small to
+/// shade: the texture's average, else the base colors, else the shader's.
+fn surface_color(shader: ShaderType, base_colors: (Option<Color>, Option<Color>), texture: Option<&Texture>) -> Color {
+    if let Some(texture) = texture {
+        return texture.average();
+    }
+    match base_colors {
+        (Some(a), Some(b)) => a.lerp(&b, 0.5),
+        (Some(c), None) | (None, Some(c)) => c,
+        (None, None) => shader.representative_color(),
+    }
+}
+
+/// Point on an elliptical orbit with the sun at one focus, where `radius` is
+/// the semi-major axis and `angle` the true anomaly. The orbit lies in the
+/// y = 0 plane tipped about the x axis by `inclination`.
+fn orbit_position(radius: f32, eccentricity: f32, inclination: f32, angle: f32) -> Vec3 {
+    let r = radius * (1.0 - eccentricity * eccentricity) / (1.0 + eccentricity * angle.cos());
+    let (x, z) = (r * angle.cos(), r * angle.sin());
+    let (sin_i, cos_i) = inclination.sin_cos();
+    Vec3::new(x, -z * sin_i, z * cos_i)
+}
+
+struct Collision {
+    planet: usize,
+    /// Unit vector from the planet's center toward the ship
+    normal: Vec3,
+}
+
+/// How far `pos` is from touching `planet`, counting the ship's clearance.
+/// Negative once they collide.
+fn surface_gap(pos: &Vec3, planet: &Planet) -> f32 {
+    (*pos - planet.position).length() - planet.scale - SHIP_CLEARANCE
+}
+
+fn planet_collision(pos: &Vec3, planet: usize, p: &Planet) -> Option<Collision> {
+    if surface_gap(pos, p) < 0.0 {
+        Some(Collision {
+            planet,
+            normal: (*pos - p.position).normalize(),
+        })
+    } else {
+        None
+    }
+}
+
+fn check_collision(pos: &Vec3, planets: &[Planet], grid: &SpatialGrid) -> Option<Collision> {
+    grid.nearby(pos, grid.max_radius() + SHIP_CLEARANCE)
+        .into_iter()
+        .find_map(|i| planet_collision(pos, i, &planets[i]))
+}
+
+/// Pull on a point from every planet within `GRAVITY_RANGE`, using `grid` to
+/// skip the ones too far away to matter.
+pub fn gravity_acceleration(pos: &Vec3, planets: &[Planet], grid: &SpatialGrid) -> Vec3 {
+    let mut acceleration = Vec3::new(0.0, 0.0, 0.0);
+    for planet in grid.nearby(pos, GRAVITY_RANGE).into_iter().map(|i| &planets[i]) {
+        let to_planet = planet.position - *pos;
+        let dist = to_planet.length();
+        if dist > GRAVITY_RANGE {
+            continue;
+        }
+        // Clamp to the surface so the pull doesn't blow up near the center
+        let dist = dist.max(planet.scale);
+        let mass = planet.scale * planet.scale * planet.scale;
+        let strength = GRAVITY_CONSTANT * mass / (dist * dist);
+        acceleration = acceleration + to_planet.normalize() * strength;
+    }
+    acceleration
+}
+
+/// Pseudo-random value in [0, 1) for an integer lattice point.
+fn lattice_hash(x: i32, y: i32, z: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(73_856_093)
+        ^ (y as u32).wrapping_mul(19_349_663)
+        ^ (z as u32).wrapping_mul(83_492_791);
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    h ^= h >> 16;
+    (h & 0x00FF_FFFF) as f32 / (1u32 << 24) as f32
+}
+
+/// Value noise: random values at integer lattice points, blended with a
+/// smoothstep so the result is continuous across cell boundaries.
+fn noise(x: f32, y: f32, z: f32) -> f32 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (u, v, w) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let corner = |dx: i32, dy: i32, dz: i32| lattice_hash(ix + dx, iy + dy, iz + dz);
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+    lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+}
+
+fn fbm(p: &Vec3, octaves: i32) -> f32 {
+    let mut value = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    
+    for _ in 0..octaves {
+        value += noise(p.x * frequency, p.y * frequency, p.z * frequency) * amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    value
+}
+
+/// A point light. `range` is the distance at which its strength has halved,
+/// with inverse-square falloff past it.
+pub struct Light {
+    pub position: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    pub range: f32,
+}
+
+impl Light {
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let d = distance / self.range;
+        self.intensity / (1.0 + d * d)
+    }
+}
+
+/// Every light in the scene: each star, plus a dim glow around lava worlds.
+pub fn scene_lights(system: &SolarSystem) -> Vec<Light> {
+    let stars = system.stars.iter().map(|star| Light {
+        position: system.planets[star.body].position,
+        color: star.light,
+        intensity: SUN_BRIGHTNESS,
+        range: SUN_LIGHT_RANGE,
+    });
+    let lava = system.planets.iter().filter(|planet| planet.shader == ShaderType::Lava).map(|planet| Light {
+        position: planet.position,
+        color: WHITE_LIGHT,
+        intensity: LAVA_GLOW_INTENSITY,
+        range: planet.scale * LAVA_GLOW_RANGE,
+    });
+    stars.chain(lava).collect()
+}
+
+/// Average of the light colors reaching a point, weighted by how much each
+/// one contributes: the tint the lit surface takes on.
+#[derive(Default)]
+struct LightTint {
+    total: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl LightTint {
+    fn add(&mut self, amount: f32, color: Color) {
+        self.total += amount;
+        self.r += amount * color.r as f32;
+        self.g += amount * color.g as f32;
+        self.b += amount * color.b as f32;
+    }
+
+    /// White when nothing's lit, so the night side keeps its own color.
+    fn color(&self) -> Color {
+        if self.total <= 0.0 {
+            return WHITE_LIGHT;
+        }
+        // Rounded so lights that are all one color give back exactly it
+        let channel = |sum: f32| (sum / self.total).round().clamp(0.0, 255.0) as u8;
+        Color::new(channel(self.r), channel(self.g), channel(self.b))
+    }
+}
+
+/// Per-pixel lighting vectors, all world-space and pointing away from the surface.
+/// `light_dir` is toward the light contributing most at this point, which
+/// is the one specular highlights come from.
+struct Lighting {
+    normal: Vec3,
+    light_dir: Vec3,
+    view_dir: Vec3,
+    in_shadow: bool,
+}
+
+impl Lighting {
+    /// Blinn-Phong highlight from the half-vector between light and view.
+    fn specular(&self, shininess: f32) -> f32 {
+        if self.in_shadow || self.normal.dot(&self.light_dir) <= 0.0 {
+            return 0.0;
+        }
+        let half = (self.light_dir + self.view_dir).normalize();
+        self.normal.dot(&half).max(0.0).powf(shininess)
+    }
+}
+
+/// `base_colors` override the shader's two main palette colors; `None`
+/// keeps the built-in one.
+fn apply_planet_shader(
+    normal: &Vec3,
+    light_intensity: f32,
+    shader: ShaderType,
+    time: f32,
+    lighting: &Lighting,
+    base_colors: (Option<Color>, Option<Color>),
+) -> Color {
+    let highlight = Color::new(255, 255, 255);
+    let (base_a, base_b) = base_colors;
+
+    match shader {
+        ShaderType::Sun => {
+            let glow = 0.9 + (time * 2.0).sin() * 0.1;
+            let core = base_a.unwrap_or(Color::new(255, 240, 200));
+            let corona = base_b.unwrap_or(Color::new(255, 180, 80));
+            let t = (normal.y * 0.5 + 0.5) * glow;
+            core.lerp(&corona, t)
+        },
+        ShaderType::Earth => {
+            let ocean = base_a.unwrap_or(Color::new(30, 80, 180));
+            let land = base_b.unwrap_or(Color::new(60, 150, 80));
+            let clouds = Color::new(220, 220, 240);
+            
+            let continent = fbm(&(*normal * 3.0), 3);
+            let cloud_pattern = fbm(&(*normal * 8.0 + Vec3::new(time * 0.1, 0.0, 0.0)), 2);
+            
+            let is_ocean = continent <= 0.5;
+            let mut base = if is_ocean { ocean } else { land };
+            if cloud_pattern > 0.6 {
+                base = base.lerp(&clouds, 0.7);
+            }
+            let lit = base.mul(light_intensity.max(0.2));
+            // Only open water reflects the sun
+            let lit = if is_ocean && cloud_pattern <= 0.6 {
+                lit.lerp(&highlight, lighting.specular(32.0) * 0.6)
+            } else {
+                lit
+            };
+
+            // Atmosphere thickens toward the limb, faintly even on the night side
+            let rim = 1.0 - lighting.normal.dot(&lighting.view_dir).abs();
+            let sky = Color::new(140, 190, 255);
+            lit.lerp(&sky, rim.powi(3) * (0.3 + 0.5 * light_intensity))
+        },
+        ShaderType::GasGiant => {
+            let base1 = base_a.unwrap_or(Color::new(220, 180, 120));
+            let base2 = base_b.unwrap_or(Color::new(180, 140, 90));
+            let band = ((normal.y + time * 0.05).sin() * 10.0).fract();
+            let turbulence = fbm(&Vec3::new(normal.x * 5.0, normal.y * 15.0, normal.z * 5.0), 2);
+            let color = base1.lerp(&base2, band + turbulence * 0.3);
+            color.mul(light_intensity.max(0.15))
+        },
+        ShaderType::Ice => {
+            let ice1 = base_a.unwrap_or(Color::new(180, 220, 255));
+            let ice2 = base_b.unwrap_or(Color::new(120, 180, 240));
+            let cracks = fbm(&(*normal * 8.0), 3);
+            let color = ice1.lerp(&ice2, cracks);
+            color.mul(light_intensity.max(0.3)).lerp(&highlight, lighting.specular(96.0))
+        },
+        ShaderType::Desert => {
+            let sand1 = base_a.unwrap_or(Color::new(220, 160, 100));
+            let sand2 = base_b.unwrap_or(Color::new(180, 120, 60));
+            let dunes = fbm(&(*normal * 6.0), 3);
+            let color = sand1.lerp(&sand2, dunes);
+            color.mul(light_intensity.max(0.2))
+        },
+        ShaderType::Lava => {
+            let dark = base_a.unwrap_or(Color::new(80, 30, 20));
+            let hot = base_b.unwrap_or(Color::new(255, 80, 30));
+            let glow = Color::new(255, 200, 100);
+            
+            let pattern = fbm(&(*normal * 4.0 + Vec3::new(time * 0.2, 0.0, 0.0)), 3);
+            let pulse = (time * 3.0 + pattern * 10.0).sin() * 0.5 + 0.5;
+            
+            let base = dark.lerp(&hot, pattern);
+            let final_color = base.lerp(&glow, pulse * pattern);
+            final_color.mul(light_intensity.max(0.3))
+        },
+        ShaderType::Purple => {
+            let base1 = base_a.unwrap_or(Color::new(150, 100, 200));
+            let base2 = base_b.unwrap_or(Color::new(100, 60, 160));
+            let bands = (normal.y * 8.0 + time * 0.1).sin() * 0.5 + 0.5;
+            let color = base1.lerp(&base2, bands);
+            color.mul(light_intensity.max(0.15))
+        },
+        ShaderType::Ocean => {
+            let deep = base_a.unwrap_or(Color::new(10, 40, 120));
+            let shallow = base_b.unwrap_or(Color::new(40, 130, 200));
+            let foam = Color::new(200, 230, 255);
+
+            let depth = fbm(&(*normal * 2.5), 3);
+            let swell = fbm(&(*normal * 12.0 + Vec3::new(0.0, time * 0.3, time * 0.2)), 2);
+            let waves = ((normal.x + normal.z) * 30.0 + time * 2.0 + swell * 8.0).sin() * 0.5 + 0.5;
+
+            let base = deep.lerp(&shallow, depth);
+            let base = base.lerp(&foam, (waves * swell - 0.35).max(0.0));
+            // Wave crests catch the sun on a slightly rougher surface than Earth's seas
+            let glint = lighting.specular(24.0) * (0.4 + 0.4 * waves);
+            base.mul(light_intensity.max(0.15)).lerp(&highlight, glint)
+        },
+        ShaderType::Toxic => {
+            let acid = base_a.unwrap_or(Color::new(110, 160, 20));
+            let haze = base_b.unwrap_or(Color::new(210, 220, 60));
+
+            let swirl = time * 0.15 + normal.y * 3.0;
+            let twisted = Vec3::new(
+                normal.x * swirl.cos() - normal.z * swirl.sin(),
+                normal.y,
+                normal.x * swirl.sin() + normal.z * swirl.cos(),
+            );
+            let clouds = fbm(&(twisted * 5.0), 4);
+            let bubbles = (fbm(&(*normal * 14.0), 2) * 20.0 + time).sin() * 0.5 + 0.5;
+
+            let color = acid.lerp(&haze, clouds).lerp(&Color::new(240, 255, 120), bubbles * clouds * 0.3);
+            color.mul(light_intensity.max(0.2))
+        },
+        ShaderType::Crystal => {
+            let facet = base_a.unwrap_or(Color::new(150, 210, 220));
+            let vein = base_b.unwrap_or(Color::new(90, 60, 170));
+
+            // Snapping the normal to a coarse grid gives flat faces that
+            // catch the light all at once
+            let snapped = Vec3::new(
+                (normal.x * 4.0).round(),
+                (normal.y * 4.0).round(),
+                (normal.z * 4.0).round(),
+            ) * 0.25;
+            let grain = fbm(&(snapped * 6.0), 2);
+            let bands = ((snapped.y + grain) * 12.0).sin().abs().powi(8);
+
+            let color = facet.lerp(&vein, grain * 0.6);
+            color
+                .mul(light_intensity.max(0.25))
+                .lerp(&highlight, (bands * 0.5 * light_intensity + lighting.specular(128.0)).min(1.0))
+        },
+        ShaderType::Moon => {
+            let gray1 = base_a.unwrap_or(Color::new(180, 180, 180));
+            let gray2 = base_b.unwrap_or(Color::new(120, 120, 120));
+            let craters = fbm(&(*normal * 10.0), 4);
+            let color = gray1.lerp(&gray2, craters);
+            color.mul(light_intensity.max(0.1))
+        },
+    }
+}
+
+/// Screen position and view depth of `vertex` under `view_proj`, or `None`
+/// when it's behind the near plane.
+pub fn project_vertex(vertex: &Vec3, view_proj: &Mat4, width: usize, height: usize) -> Option<(f32, f32, f32)> {
+    let [x, y, _, w] = view_proj.transform(vertex);
+    if w <= NEAR_PLANE {
+        return None;
+    }
+
+    let screen_x = (width as f32 / 2.0) * (1.0 + x / w);
+    let screen_y = (height as f32 / 2.0) * (1.0 - y / w);
+
+    Some((screen_x, screen_y, w))
+}
+
+/// Projects the segment `a`–`b`, first clipping it to the near plane and
+/// the screen edges so a segment that passes beside or behind the camera
+/// keeps its visible part. Returns `None` if none of it is visible.
+pub fn project_segment(
+    a: &Vec3,
+    b: &Vec3,
+    view_proj: &Mat4,
+    width: usize,
+    height: usize,
+) -> Option<(ScreenVertex, ScreenVertex)> {
+    let (a, b) = (view_proj.transform(a), view_proj.transform(b));
+    // Each plane is "inside" where its distance is non-negative (Liang-Barsky)
+    let distances = |p: &[f32; 4]| [p[3] - NEAR_PLANE, p[3] - p[0], p[3] + p[0], p[3] - p[1], p[3] + p[1]];
+    let (da, db) = (distances(&a), distances(&b));
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (&start, &end) in da.iter().zip(&db) {
+        if start < 0.0 && end < 0.0 {
+            return None;
+        }
+        let t = start / (start - end);
+        if start < 0.0 {
+            t0 = t0.max(t);
+        } else if end < 0.0 {
+            t1 = t1.min(t);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let to_screen = |t: f32| {
+        let [x, y, _, w] = std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+        (
+            (width as f32 / 2.0) * (1.0 + x / w),
+            (height as f32 / 2.0) * (1.0 - y / w),
+            w,
+        )
+    };
+    Some((to_screen(t0), to_screen(t1)))
+}
+
+/// Distance along a normalized ray to its first hit with a sphere, if any.
+pub fn ray_sphere_intersection(origin: &Vec3, dir: &Vec3, center: &Vec3, radius: f32) -> Option<f32> {
+    let to_origin = origin.sub(center);
+    let b = to_origin.dot(dir);
+    let c = to_origin.dot(&to_origin) - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrt_d = discriminant.sqrt();
+    let t = if -b - sqrt_d > 0.0 { -b - sqrt_d } else { -b + sqrt_d };
+    if t > 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Diffuse light from every entry in `lights` is summed per pixel. A
+/// `texture`, if given, replaces the shader's surface pattern.
+/// `occluders` are (center, radius) spheres that can block that light from
+/// reaching this sphere's surface, e.g. its moons or a neighboring planet.
+#[allow(clippy::too_many_arguments)]
+pub fn render_sphere(
+    fb: &mut Framebuffer,
+    center: &Vec3,
+    radius: f32,
+    shader: ShaderType,
+    rotation: f32,
+    axial_tilt: f32,
+    camera: &Camera,
+    time: f32,
+    base_colors: (Option<Color>, Option<Color>),
+    texture: Option<&Texture>,
+    lights: &[Light],
+    occluders: &[(Vec3, f32)],
+) {
+    let camera_pos = camera.get_position();
+    let camera_forward = camera.get_forward();
+    let camera_right = camera.get_right();
+    let camera_up = camera_right.cross(&camera_forward).normalize();
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    
+    let (width, height) = (fb.width as f32, fb.height as f32);
+
+    if let Some((cx, cy, depth)) = project_vertex(center, &view_proj, fb.width, fb.height) {
+        let dist = (*center - camera_pos).length();
+        let apparent_dist = dist / camera.zoom_factor();
+        if apparent_dist > FOG_END {
+            return;
+        }
+        let fog = ((apparent_dist - FOG_START) / (FOG_END - FOG_START)).clamp(0.0, 1.0);
+        let background = Color::new(0, 0, 0);
+        
+        if dist <= radius {
+            return;
+        }
+
+        // Bounding circle of the silhouette. Off-axis spheres project as
+        // ellipses stretched by roughly 1/cos² of their angle from the view
+        // axis, so the circle grows toward the screen edges to cover them.
+        let fov_factor = (camera.fov / 2.0).tan();
+        let aspect = width / height;
+        let cos_angle = (depth / dist).max(0.3);
+        let angular_radius = radius / (dist * dist - radius * radius).sqrt();
+        let screen_radius = (height / 2.0) * angular_radius / fov_factor / (cos_angle * cos_angle) + 2.0;
+
+        // Too small to show any surface detail: one flat disk is enough
+        let disk_radius = (height / 2.0) * angular_radius / fov_factor;
+        if disk_radius < SPHERE_LOD_RADIUS * fb.scale as f32 {
+            let color = if shader == ShaderType::Sun {
+                surface_color(shader, base_colors, texture)
+            } else {
+                let to_camera = (camera_pos - *center).normalize();
+                // Share of the visible face that's lit, from the light's angle to the view
+                let mut tint = LightTint::default();
+                for light in lights {
+                    let to_light = light.position - *center;
+                    let phase = (1.0 + to_light.normalize().dot(&to_camera)) / 2.0;
+                    tint.add(phase * light.attenuation(to_light.length()), light.color);
+                }
+                surface_color(shader, base_colors, texture).mul(tint.total.clamp(0.2, 1.0)).filter(&tint.color())
+            };
+            draw_disk(fb, cx, cy, disk_radius, depth - radius, color.lerp(&background, fog).to_u32());
+            return;
+        }
+
+        // Skip spheres whose projected bounding circle misses the screen entirely
+        if cx + screen_radius < 0.0
+            || cx - screen_radius > width
+            || cy + screen_radius < 0.0
+            || cy - screen_radius > height
+        {
+            return;
+        }
+        
+        let x_min = (cx - screen_radius).max(0.0) as i32;
+        let x_max = (cx + screen_radius).min(width - 1.0) as i32;
+        let y_min = (cy - screen_radius).max(0.0) as i32;
+        let y_max = (cy + screen_radius).min(height - 1.0) as i32;
+        
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                // Inverse of project_vertex: the view ray through this pixel's center
+                let ndc_x = (x as f32 + 0.5) / (width / 2.0) - 1.0;
+                let ndc_y = 1.0 - (y as f32 + 0.5) / (height / 2.0);
+                let ray = (camera_forward
+                    + camera_right * (ndc_x * fov_factor * aspect)
+                    + camera_up * (ndc_y * fov_factor))
+                    .normalize();
+
+                let Some(t) = ray_sphere_intersection(&camera_pos, &ray, center, radius) else {
+                    continue;
+                };
+                let surface = camera_pos + ray * t;
+                // Camera-space z, the same depth project_vertex gives draw_line
+                let pixel_depth = (surface - camera_pos).dot(&camera_forward);
+
+                let idx = y as usize * fb.width + x as usize;
+                if pixel_depth >= fb.depth[idx] {
+                    continue;
+                }
+                fb.depth[idx] = pixel_depth;
+
+                let normal = (surface - *center).normalize();
+
+                let mut tint = LightTint::default();
+                let mut strongest = 0.0;
+                let mut lighting = Lighting {
+                    normal,
+                    light_dir: Vec3::new(0.0, 0.0, 0.0),
+                    view_dir: -ray,
+                    in_shadow: true,
+                };
+                for light in lights {
+                    let to_light = light.position - surface;
+                    let light_dist = to_light.length();
+                    let light_dir = to_light.normalize();
+                    let diffuse = normal.dot(&light_dir);
+                    if diffuse <= 0.0 {
+                        continue;
+                    }
+                    // Eclipse test: is anything between this point and the light?
+                    // A body can't shadow the light glowing from inside it.
+                    let in_shadow = occluders.iter().any(|(occluder, occluder_radius)| {
+                        (light.position - *occluder).length() > *occluder_radius
+                            && ray_sphere_intersection(&surface, &light_dir, occluder, *occluder_radius)
+                                .is_some_and(|t| t < light_dist)
+                    });
+                    if in_shadow {
+                        continue;
+                    }
+                    let contribution = diffuse * light.attenuation(light_dist);
+                    tint.add(contribution, light.color);
+                    if contribution > strongest {
+                        strongest = contribution;
+                        lighting.light_dir = light_dir;
+                        lighting.in_shadow = false;
+                    }
+                }
+                let light_intensity = tint.total.min(1.0);
+                
+                // Into the planet's own frame: undo the tilt, then the spin
+                let rotated_normal = normal.rotate_z(-axial_tilt).rotate_y(rotation);
+                let color = match texture {
+                    Some(texture) => textured_surface(texture, &rotated_normal, light_intensity, shader),
+                    None => apply_planet_shader(&rotated_normal, light_intensity, shader, time, &lighting, base_colors),
+                };
+                // Suns make their own light; everything else takes on the
+                // color of the stars lighting it
+                let color = if shader == ShaderType::Sun {
+                    color
+                } else {
+                    color.filter(&tint.color())
+                }
+                .lerp(&background, fog);
+                
+                fb.color[idx] = color.to_u32();
+            }
+        }
+    }
+}
+
+/// Depth-tested filled circle, always at least the pixel under its center.
+fn draw_disk(fb: &mut Framebuffer, cx: f32, cy: f32, radius: f32, depth: f32, color: u32) {
+    let reach = radius.ceil() as i32;
+    let (px, py) = (cx.floor() as i32, cy.floor() as i32);
+    for y in py - reach..=py + reach {
+        for x in px - reach..=px + reach {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius || (x, y) == (px, py) {
+                fb.plot(x, y, depth, color);
+            }
+        }
+    }
+}
+
+/// Looks up `texture` at the point whose planet-frame normal is `normal`.
+/// Suns glow at full brightness; everything else keeps a faint night side.
+fn textured_surface(texture: &Texture, normal: &Vec3, light_intensity: f32, shader: ShaderType) -> Color {
+    let u = 0.5 + normal.x.atan2(normal.z) / (2.0 * PI);
+    let v = 0.5 - normal.y.clamp(-1.0, 1.0).asin() / PI;
+    let texel = texture.sample(u, v);
+    if shader == ShaderType::Sun {
+        texel
+    } else {
+        texel.mul(light_intensity.max(0.2))
+    }
+}
+
+/// Additive halo around the sun, drawn over whatever is already in the
+/// buffer. It ignores the z-buffer on purpose so the glow bleeds over the
+/// sun's silhouette and anything passing in front of it.
+pub fn render_sun_glow(fb: &mut Framebuffer, center: &Vec3, radius: f32, camera: &Camera, time: f32) {
+    let camera_pos = camera.get_position();
+    let projected = project_vertex(center, &camera.view_projection(fb.width, fb.height), fb.width, fb.height);
+    let Some((cx, cy, _)) = projected else {
+        return;
+    };
+
+    let dist = (*center - camera_pos).length();
+    if dist / camera.zoom_factor() > FOG_END {
+        return;
+    }
+    let screen_radius = radius * fb.height as f32 / (2.0 * dist * (camera.fov / 2.0).tan());
+    // Same pulse as the Sun shader so the halo breathes with the surface
+    let glow = 0.9 + (time * 2.0).sin() * 0.1;
+    let halo_radius = screen_radius * 2.5 * glow;
+    if halo_radius < 1.0 {
+        return;
+    }
+
+    let x_min = (cx - halo_radius).max(0.0) as i32;
+    let x_max = (cx + halo_radius).min(fb.width as f32 - 1.0) as i32;
+    let y_min = (cy - halo_radius).max(0.0) as i32;
+    let y_max = (cy + halo_radius).min(fb.height as f32 - 1.0) as i32;
+    let (glow_r, glow_g, glow_b) = (255.0, 190.0, 110.0);
+
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let dx = x as f32 - cx;
+            let dy = y as f32 - cy;
+            let t = (dx * dx + dy * dy).sqrt() / halo_radius;
+            if t >= 1.0 {
+                continue;
+            }
+
+            let strength = (1.0 - t).powi(2) * 0.6 * glow;
+            let idx = y as usize * fb.width + x as usize;
+            let pixel = fb.color[idx];
+            let r = (((pixel >> 16) & 0xFF) as f32 + glow_r * strength).min(255.0) as u32;
+            let g = (((pixel >> 8) & 0xFF) as f32 + glow_g * strength).min(255.0) as u32;
+            let b = ((pixel & 0xFF) as f32 + glow_b * strength).min(255.0) as u32;
+            fb.color[idx] = (r << 16) | (g << 8) | b;
+        }
+    }
+}
+
+/// Translucent shell `ATMOSPHERE_THICKNESS` radii deep around a planet,
+/// added onto whatever is already in the buffer. Its opacity peaks just
+/// outside the silhouette, where a view ray grazes the most air, and thins
+/// to a faint haze across the disk. Depth tested against what's in front
+/// but never written, so anything drawn behind it still shows through.
+pub fn render_atmosphere(
+    fb: &mut Framebuffer,
+    center: &Vec3,
+    radius: f32,
+    color: Color,
+    camera: &Camera,
+    lights: &[Light],
+) {
+    let camera_pos = camera.get_position();
+    let camera_forward = camera.get_forward();
+    let camera_right = camera.get_right();
+    let camera_up = camera_right.cross(&camera_forward).normalize();
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    let (width, height) = (fb.width as f32, fb.height as f32);
+    let shell = radius * (1.0 + ATMOSPHERE_THICKNESS);
+
+    let Some((cx, cy, depth)) = project_vertex(center, &view_proj, fb.width, fb.height) else {
+        return;
+    };
+    let dist = (*center - camera_pos).length();
+    let apparent_dist = dist / camera.zoom_factor();
+    if dist <= shell || apparent_dist > FOG_END {
+        return;
+    }
+    let fog = ((apparent_dist - FOG_START) / (FOG_END - FOG_START)).clamp(0.0, 1.0);
+
+    // Same bounding circle as render_sphere, for the shell
+    let fov_factor = (camera.fov / 2.0).tan();
+    let aspect = width / height;
+    let cos_angle = (depth / dist).max(0.3);
+    let angular_radius = shell / (dist * dist - shell * shell).sqrt();
+    if (height / 2.0) * angular_radius / fov_factor < SPHERE_LOD_RADIUS * fb.scale as f32 {
+        return;
+    }
+    let screen_radius = (height / 2.0) * angular_radius / fov_factor / (cos_angle * cos_angle) + 2.0;
+    let x_min = (cx - screen_radius).max(0.0) as i32;
+    let x_max = (cx + screen_radius).min(width - 1.0) as i32;
+    let y_min = (cy - screen_radius).max(0.0) as i32;
+    let y_max = (cy + screen_radius).min(height - 1.0) as i32;
+
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let ndc_x = (x as f32 + 0.5) / (width / 2.0) - 1.0;
+            let ndc_y = 1.0 - (y as f32 + 0.5) / (height / 2.0);
+            let ray = (camera_forward + camera_right * (ndc_x * fov_factor * aspect) + camera_up * (ndc_y * fov_factor))
+                .normalize();
+            let Some(t) = ray_sphere_intersection(&camera_pos, &ray, center, shell) else {
+                continue;
+            };
+            let idx = y as usize * fb.width + x as usize;
+            if (ray * t).dot(&camera_forward) >= fb.depth[idx] {
+                continue;
+            }
+
+            // How close the ray passes to the center decides how much air it crosses
+            let closest = camera_pos + ray * (*center - camera_pos).dot(&ray);
+            let miss = (closest - *center).length();
+            let alpha = if miss >= radius {
+                1.0 - (miss - radius) / (shell - radius)
+            } else {
+                (miss / radius).powi(ATMOSPHERE_DISK_FALLOFF)
+            };
+            // Air glows where the light reaches it, barely at all on the night side
+            let normal = (closest - *center).normalize();
+            let lit: f32 = lights
+                .iter()
+                .map(|light| {
+                    let to_light = light.position - closest;
+                    (normal.dot(&to_light.normalize()) + ATMOSPHERE_WRAP).max(0.0) / (1.0 + ATMOSPHERE_WRAP)
+                        * light.attenuation(to_light.length())
+                })
+                .sum();
+            let strength = alpha * alpha * ATMOSPHERE_OPACITY * lit.min(1.0) * (1.0 - fog);
+            if strength <= 0.0 {
+                continue;
+            }
+
+            let pixel = fb.color[idx];
+            let add = |shift: u32, channel: u8| {
+                ((((pixel >> shift) & 0xFF) as f32 + channel as f32 * strength).min(255.0)) as u32
+            };
+            fb.color[idx] = (add(16, color.r) << 16) | (add(8, color.g) << 8) | add(0, color.b);
+        }
+    }
+}
+
+pub fn render_rings(
+    fb: &mut Framebuffer,
+    center: &Vec3,
+    inner_radius: f32,
+    outer_radius: f32,
+    ring_color: Color,
+    axial_tilt: f32,
+    camera: &Camera,
+) {
+    let camera_pos = camera.get_position();
+    let view_proj = camera.view_projection(fb.width, fb.height);
+
+    let dist = (*center - camera_pos).length();
+    if dist / camera.zoom_factor() > FOG_END {
+        return;
+    }
+
+    // Sample density follows the projected size so close rings don't show holes
+    let screen_outer = outer_radius * fb.height as f32 / (2.0 * dist * (camera.fov / 2.0).tan());
+    let screen_width = screen_outer * (outer_radius - inner_radius) / outer_radius;
+    let segments = ((2.0 * PI * screen_outer * 2.0) as usize).clamp(64, 4096);
+    let bands = ((screen_width * 2.0) as usize).clamp(4, 512);
+
+    for band in 0..bands {
+        let t = band as f32 / (bands - 1) as f32;
+        let radius = inner_radius + (outer_radius - inner_radius) * t;
+        let color = ring_color.mul(1.0 - t * 0.6).to_u32();
+
+        for i in 0..segments {
+            let angle = 2.0 * PI * i as f32 / segments as f32;
+            // Rings lie in the planet's tilted equatorial plane
+            let v = *center + Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin()).rotate_z(axial_tilt);
+
+            if let Some((sx, sy, z)) = project_vertex(&v, &view_proj, fb.width, fb.height) {
+                fb.plot(sx as i32, sy as i32, z, color);
+            }
+        }
+    }
+}
+
+pub fn render_orbit(
+    fb: &mut Framebuffer,
+    radius: f32,
+    eccentricity: f32,
+    inclination: f32,
+    camera: &Camera,
+    color: u32,
+    style: LineStyle,
+) {
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    
+    let segments = 150;
+    let mut previous = orbit_position(radius, eccentricity, inclination, 0.0);
+    for i in 1..=segments {
+        let angle = 2.0 * PI * i as f32 / segments as f32;
+        let v = orbit_position(radius, eccentricity, inclination, angle);
+        
+        // Depth-tested so planets in front hide the orbit and ones behind don't
+        if let Some((start, end)) = project_segment(&previous, &v, &view_proj, fb.width, fb.height) {
+            draw_line(fb, start, end, color, style);
+        }
+        previous = v;
+    }
+}
+
+/// The ship's predicted path as a line that dims toward its far end.
+pub fn render_trajectory(fb: &mut Framebuffer, path: &[Vec3], camera: &Camera, style: LineStyle) {
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    let segments = path.len().saturating_sub(1).max(1) as f32;
+    for (i, pair) in path.windows(2).enumerate() {
+        let fade = 1.0 - 0.7 * i as f32 / segments;
+        if let Some((start, end)) = project_segment(&pair[0], &pair[1], &view_proj, fb.width, fb.height) {
+            draw_line(fb, start, end, TRAJECTORY_COLOR.mul(fade).to_u32(), style);
+        }
+    }
+}
+
+/// Each planet's spin axis, through its center and out past both poles, for
+/// checking the tilt math. Depth tested, so the hidden half stays hidden.
+pub fn render_spin_axes(fb: &mut Framebuffer, planets: &[Planet], camera: &Camera, style: LineStyle) {
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    for planet in planets {
+        let reach = planet.spin_axis() * (planet.scale * SPIN_AXIS_REACH);
+        let (south, north) = (planet.position - reach, planet.position + reach);
+        if let Some((start, end)) = project_segment(&south, &north, &view_proj, fb.width, fb.height) {
+            draw_line(fb, start, end, SPIN_AXIS_COLOR, style);
+        }
+    }
+}
+
+/// Reference grid on the y = 0 orbital plane around the camera. Each line
+/// is drawn one cell at a time so the far cells can fade to black.
+pub fn render_grid(fb: &mut Framebuffer, camera: &Camera, style: LineStyle) {
+    let view_proj = camera.view_projection(fb.width, fb.height);
+    let camera_pos = camera.get_position();
+    // Snap to the grid so the lines don't slide along with the camera
+    let origin_x = (camera_pos.x / GRID_SPACING).round() * GRID_SPACING;
+    let origin_z = (camera_pos.z / GRID_SPACING).round() * GRID_SPACING;
+    let cells = (GRID_EXTENT / GRID_SPACING) as i32;
+
+    for line in -cells..=cells {
+        for cell in -cells..cells {
+            let (u, v0, v1) = (line as f32 * GRID_SPACING, cell as f32 * GRID_SPACING, (cell + 1) as f32 * GRID_SPACING);
+            let segments = [
+                (Vec3::new(origin_x + u, 0.0, origin_z + v0), Vec3::new(origin_x + u, 0.0, origin_z + v1)),
+                (Vec3::new(origin_x + v0, 0.0, origin_z + u), Vec3::new(origin_x + v1, 0.0, origin_z + u)),
+            ];
+            for (a, b) in segments {
+                let midpoint = (a + b) * 0.5;
+                let fade = 1.0 - (midpoint - camera_pos).length() / GRID_FADE_DISTANCE;
+                if fade <= 0.0 {
+                    continue;
+                }
+                if let Some((start, end)) = project_segment(&a, &b, &view_proj, fb.width, fb.height) {
+                    draw_line(fb, start, end, GRID_COLOR.mul(fade).to_u32(), style);
+                }
+            }
+        }
+    }
+}
+
+/// How `draw_line` rasterizes: `width` is in window pixels and
+/// `antialiased` blends the line's edges into what's already drawn.
+#[derive(Clone, Copy)]
+pub struct LineStyle {
+    pub width: f32,
+    pub antialiased: bool,
+}
+
+/// Depth-tested line between two projected points. It walks the longer axis
+/// one pixel at a time and fills a span across it, so the line keeps the
+/// same thickness at any angle.
+pub fn draw_line(fb: &mut Framebuffer, a: ScreenVertex, b: ScreenVertex, color: u32, style: LineStyle) {
+    let steep = (b.1 - a.1).abs() > (b.0 - a.0).abs();
+    // (major, minor, depth), with the major axis running left to right
+    let swap = |v: ScreenVertex| if steep { (v.1, v.0, v.2) } else { v };
+    let (mut start, mut end) = (swap(a), swap(b));
+    if start.0 > end.0 {
+        std::mem::swap(&mut start, &mut end);
+    }
+
+    let run = end.0 - start.0;
+    let slope = if run > 0.0 { (end.1 - start.1) / run } else { 0.0 };
+    // Stretch the span along the minor axis to keep the width perpendicular
+    let half_span = style.width * fb.scale as f32 * (1.0 + slope * slope).sqrt() / 2.0;
+    // Depth isn't linear in screen space but its reciprocal is, which
+    // matters for long segments running away from the camera
+    let (w0, w1) = (1.0 / start.2, 1.0 / end.2);
+
+    let major_limit = if steep { fb.height } else { fb.width } as i32 - 1;
+    let first = (start.0.floor() as i32).max(0);
+    let last = (end.0.floor() as i32).min(major_limit);
+    for major in first..=last {
+        let along = major as f32 + 0.5 - start.0;
+        let t = if run > 0.0 { (along / run).clamp(0.0, 1.0) } else { 0.0 };
+        let z = 1.0 / (w0 + (w1 - w0) * t);
+        let center = start.1 + slope * along;
+        let to_pixel = |minor: i32| if steep { (minor, major) } else { (major, minor) };
+
+        if style.antialiased {
+            // Each pixel gets the fraction of it the span covers
+            let (low, high) = (center - half_span, center + half_span);
+            for minor in low.floor() as i32..=high.floor() as i32 {
+                let coverage = (minor as f32 + 1.0).min(high) - (minor as f32).max(low);
+                let (x, y) = to_pixel(minor);
+                fb.blend(x, y, z, color, coverage);
+            }
+        } else {
+            // A whole number of pixels, so a one-pixel line is plain Bresenham
+            let count = (2.0 * half_span).round().max(1.0) as i32;
+            let first = (center - count as f32 / 2.0 + 0.5).floor() as i32;
+            for minor in first..first + count {
+                let (x, y) = to_pixel(minor);
+                fb.plot(x, y, z, color);
+            }
+        }
+    }
+}
+
+/// How `render_spaceship` draws a loaded OBJ model. The built-in ship is
+/// always a wireframe.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MeshShading {
+    Wireframe,
+    /// One lit color per face, from its geometric normal
+    Flat,
+    /// Lit at each vertex and blended across the face
+    Gouraud,
+}
+
+impl MeshShading {
+    pub fn from_name(name: &str) -> Option<MeshShading> {
+        match name {
+            "wireframe" => Some(MeshShading::Wireframe),
+            "flat" => Some(MeshShading::Flat),
+            "gouraud" => Some(MeshShading::Gouraud),
+            _ => None,
+        }
+    }
+}
+
+/// Hull brightness at `point`: diffuse light from every source plus a
+/// little ambient so the dark side doesn't vanish. Nothing casts shadows.
+fn hull_light(point: &Vec3, normal: &Vec3, lights: &[Light]) -> f32 {
+    let diffuse: f32 = lights
+        .iter()
+        .map(|light| {
+            let to_light = light.position - *point;
+            normal.dot(&to_light.normalize()).max(0.0) * light.attenuation(to_light.length())
+        })
+        .sum();
+    (SHIP_AMBIENT + diffuse).min(1.0)
+}
+
+/// Unnormalized normal of a face from its first three corners, following
+/// the OBJ counter-clockwise winding. Its length is twice the area.
+fn face_normal(model: &Model, face: &obj::Face) -> Vec3 {
+    let corner = |k: usize| model.vertices[face.vertex_indices[k]].position;
+    (corner(1) - corner(0)).cross(&(corner(2) - corner(0)))
+}
+
+/// Fills an OBJ model's faces, which must already be triangles (see
+/// `Model::triangulate`). Faces without `vn` data get smooth normals averaged from the faces
+/// around each vertex. With `cull_from` set to the camera position,
+/// triangles facing away from it are skipped.
+fn render_solid_model(
+    fb: &mut Framebuffer,
+    model: &Model,
+    projected: &[Option<ScreenVertex>],
+    model_matrix: &Mat4,
+    shading: MeshShading,
+    lights: &[Light],
+    cull_from: Option<Vec3>,
+) {
+    // Normals only need the rotation part of the model matrix
+    let mut rotation = *model_matrix;
+    for row in &mut rotation.m[..3] {
+        row[3] = 0.0;
+    }
+    let world: Vec<Vec3> = model.vertices.iter().map(|v| model_matrix.transform_point(&v.position)).collect();
+
+    let mut smooth_normals = vec![Vec3::new(0.0, 0.0, 0.0); model.vertices.len()];
+    if shading == MeshShading::Gouraud {
+        for face in model.faces.iter().filter(|f| f.normal_indices.is_none()) {
+            // Area-weighted, so slivers barely bend the average
+            let normal = face_normal(model, face);
+            for &i in &face.vertex_indices {
+                smooth_normals[i] = smooth_normals[i] + normal;
+            }
+        }
+    }
+
+    for face in &model.faces {
+        let indices = &face.vertex_indices;
+        let face_light = if shading == MeshShading::Flat {
+            let normal = rotation.transform_point(&face_normal(model, face)).normalize();
+            let centroid = indices.iter().fold(Vec3::new(0.0, 0.0, 0.0), |sum, &i| sum + world[i]) * (1.0 / indices.len() as f32);
+            hull_light(&centroid, &normal, lights)
+        } else {
+            0.0
+        };
+        let vertex_color = |k: usize| {
+            let i = indices[k];
+            let normal = match &face.normal_indices {
+                Some(normals) => model.normals[normals[k]],
+                None => smooth_normals[i],
+            };
+            let normal = rotation.transform_point(&normal).normalize();
+            SHIP_HULL_COLOR.mul(hull_light(&world[i], &normal, lights))
+        };
+
+        if let Some(eye) = cull_from {
+            let [a, b, c] = [0, 1, 2].map(|k| world[indices[k]]);
+            if (b - a).cross(&(c - a)).dot(&(a - eye)) >= 0.0 {
+                continue;
+            }
+        }
+        // Triangles poking behind the near plane are dropped whole
+        let [Some(a), Some(b), Some(c)] = [0, 1, 2].map(|k| projected[indices[k]]) else {
+            continue;
+        };
+        match shading {
+            MeshShading::Flat => triangle_filled(fb, a, b, c, SHIP_HULL_COLOR.mul(face_light).to_u32()),
+            _ => triangle_gouraud(fb, (a, vertex_color(0)), (b, vertex_color(1)), (c, vertex_color(2))),
+        }
+    }
+}
+
+pub fn render_spaceship(
+    fb: &mut Framebuffer,
+    spaceship: &Spaceship,
+    camera: &Camera,
+    style: LineStyle,
+    shading: MeshShading,
+    lights: &[Light],
+    backface_culling: bool,
+) {
+    // Ship space straight to clip space, one matrix for every vertex
+    let model_view_proj = camera.view_projection(fb.width, fb.height) * spaceship.model_matrix();
+    
+    if let Some(model) = &spaceship.model {
+        let projected: Vec<_> = model
+            .vertices
+            .iter()
+            .map(|v| project_vertex(&v.position, &model_view_proj, fb.width, fb.height))
+            .collect();
+
+        if shading != MeshShading::Wireframe {
+            let cull_from = backface_culling.then(|| camera.get_position());
+            render_solid_model(fb, model, &projected, &spaceship.model_matrix(), shading, lights, cull_from);
+            return;
+        }
+
+        for face in &model.faces {
+            let n = face.vertex_indices.len();
+            for k in 0..n {
+                let i = face.vertex_indices[k];
+                let j = face.vertex_indices[(k + 1) % n];
+                if let (Some(start), Some(end)) = (projected[i], projected[j]) {
+                    draw_line(fb, start, end, 0xD8D8D8, style);
+                }
+            }
+        }
+        return;
+    }
+
+    // Built-in fallback when no model file could be loaded
+    // X-WING STYLE SPACESHIP - Star Wars inspired!
+    let vertices = vec![
+        // Nose cone (pointed like X-Wing)
+        Vec3::new(0.0, 0.0, 2.2),
+        Vec3::new(-0.15, 0.1, 1.5),
+        Vec3::new(0.15, 0.1, 1.5),
+        Vec3::new(-0.15, -0.1, 1.5),
+        Vec3::new(0.15, -0.1, 1.5),
+        
+        // Main fuselage
+        Vec3::new(-0.2, 0.15, 0.8),
+        Vec3::new(0.2, 0.15, 0.8),
+        Vec3::new(-0.2, -0.15, 0.8),
+        Vec3::new(0.2, -0.15, 0.8),
+        
+        Vec3::new(-0.2, 0.15, -0.5),
+        Vec3::new(0.2, 0.15, -0.5),
+        Vec3::new(-0.2, -0.15, -0.5),
+        Vec3::new(0.2, -0.15, -0.5),
+        
+        // Cockpit canopy
+        Vec3::new(-0.12, 0.25, 0.5),
+        Vec3::new(0.12, 0.25, 0.5),
+        Vec3::new(-0.12, 0.25, 0.0),
+        Vec3::new(0.12, 0.25, 0.0),
+        
+        // TOP-LEFT S-FOIL (wing)
+        Vec3::new(-0.3, 0.3, 0.3),
+        Vec3::new(-1.6, 0.8, 0.0),
+        Vec3::new(-1.6, 0.8, -0.7),
+        Vec3::new(-0.3, 0.3, -0.6),
+        
+        // TOP-RIGHT S-FOIL (wing)
+        Vec3::new(0.3, 0.3, 0.3),
+        Vec3::new(1.6, 0.8, 0.0),
+        Vec3::new(1.6, 0.8, -0.7),
+        Vec3::new(0.3, 0.3, -0.6),
+        
+        // BOTTOM-LEFT S-FOIL (wing)
+        Vec3::new(-0.3, -0.3, 0.3),
+        Vec3::new(-1.6, -0.8, 0.0),
+        Vec3::new(-1.6, -0.8, -0.7),
+        Vec3::new(-0.3, -0.3, -0.6),
+        
+        // BOTTOM-RIGHT S-FOIL (wing)
+        Vec3::new(0.3, -0.3, 0.3),
+        Vec3::new(1.6, -0.8, 0.0),
+        Vec3::new(1.6, -0.8, -0.7),
+        Vec3::new(0.3, -0.3, -0.6),
+        
+        // Engine nacelles (4 engines!)
+        // Top-left engine
+        Vec3::new(-1.4, 0.75, -0.8),
+        Vec3::new(-1.4, 0.75, -1.1),
+        // Top-right engine
+        Vec3::new(1.4, 0.75, -0.8),
+        Vec3::new(1.4, 0.75, -1.1),
+        // Bottom-left engine
+        Vec3::new(-1.4, -0.75, -0.8),
+        Vec3::new(-1.4, -0.75, -1.1),
+        // Bottom-right engine
+        Vec3::new(1.4, -0.75, -0.8),
+        Vec3::new(1.4, -0.75, -1.1),
+    ];
+    
+    // Project all vertices
+    let mut projected = Vec::new();
+    for v in &vertices {
+        projected.push(project_vertex(v, &model_view_proj, fb.width, fb.height));
+    }
+    
+    // Colors
+    let body_color = 0xD8D8D8;    // Light gray
+    let wing_color = 0xA0A0A0;    // Medium gray
+    let cockpit_color = 0x4080FF; // Blue cockpit
+    let engine_color = 0xFF3030;  // Red engines
+    let accent_color = 0xFFFFFF;  // White accents
+    
+    let edges = vec![
+        // Nose cone
+        (0, 1, accent_color), (0, 2, accent_color), (0, 3, accent_color), (0, 4, accent_color),
+        (1, 2, body_color), (2, 4, body_color), (4, 3, body_color), (3, 1, body_color),
+        
+        // Connect nose to fuselage
+        (1, 5, body_color), (2, 6, body_color), (3, 7, body_color), (4, 8, body_color),
+        
+        // Front fuselage frame
+        (5, 6, body_color), (6, 8, body_color), (8, 7, body_color), (7, 5, body_color),
+        
+        // Rear fuselage frame
+        (9, 10, body_color), (10, 12, body_color), (12, 11, body_color), (11, 9, body_color),
+        
+        // Connect front to rear fuselage
+        (5, 9, body_color), (6, 10, body_color), (7, 11, body_color), (8, 12, body_color),
+        
+        // Cockpit canopy
+        (13, 14, cockpit_color), (14, 16, cockpit_color), (16, 15, cockpit_color), (15, 13, cockpit_color),
+        (5, 13, cockpit_color), (6, 14, cockpit_color), (9, 15, cockpit_color), (10, 16, cockpit_color),
+        
+        // TOP-LEFT S-FOIL
+        (17, 18, wing_color), (18, 19, wing_color), (19, 20, wing_color), (20, 17, wing_color),
+        (5, 17, wing_color), (9, 20, wing_color),
+        (18, 19, accent_color), // Wing leading edge
+        
+        // TOP-RIGHT S-FOIL
+        (21, 22, wing_color), (22, 23, wing_color), (23, 24, wing_color), (24, 21, wing_color),
+        (6, 21, wing_color), (10, 24, wing_color),
+        (22, 23, accent_color), // Wing leading edge
+        
+        // BOTTOM-LEFT S-FOIL
+        (25, 26, wing_color), (26, 27, wing_color), (27, 28, wing_color), (28, 25, wing_color),
+        (7, 25, wing_color), (11, 28, wing_color),
+        (26, 27, accent_color), // Wing leading edge
+        
+        // BOTTOM-RIGHT S-FOIL
+        (29, 30, wing_color), (30, 31, wing_color), (31, 32, wing_color), (32, 29, wing_color),
+        (8, 29, wing_color), (12, 32, wing_color),
+        (30, 31, accent_color), // Wing leading edge
+        
+        // Engine nacelles (glowing red!)
+        (33, 34, engine_color), // Top-left engine
+        (35, 36, engine_color), // Top-right engine
+        (37, 38, engine_color), // Bottom-left engine
+        (39, 40, engine_color), // Bottom-right engine
+        
+        // Connect engines to wings
+        (19, 33, engine_color), (19, 34, engine_color),
+        (23, 35, engine_color), (23, 36, engine_color),
+        (27, 37, engine_color), (27, 38, engine_color),
+        (31, 39, engine_color), (31, 40, engine_color),
+    ];
+    
+    // Draw all edges with proper depth
+    for (i, j, color) in edges {
+        if let (Some(start), Some(end)) = (projected[i], projected[j]) {
+            draw_line(fb, start, end, color, style);
+        }
+    }
+}
+
+/// Index of the nearest planet under the screen point (x, y), found by
+/// casting the view ray through it against each planet's bounding sphere.
+pub fn pick_planet(planets: &[Planet], camera: &Camera, x: f32, y: f32, width: usize, height: usize) -> Option<usize> {
+    let camera_pos = camera.get_position();
+    let camera_forward = camera.get_forward();
+    let camera_right = camera.get_right();
+    let camera_up = camera_right.cross(&camera_forward).normalize();
+    
+    // Inverse of project_vertex, as in render_sphere
+    let (width, height) = (width as f32, height as f32);
+    let fov_factor = (camera.fov / 2.0).tan();
+    let ndc_x = x / (width / 2.0) - 1.0;
+    let ndc_y = 1.0 - y / (height / 2.0);
+    let ray = (camera_forward
+        + camera_right * (ndc_x * fov_factor * width / height)
+        + camera_up * (ndc_y * fov_factor))
+        .normalize();
+    
+    planets
+        .iter()
+        .enumerate()
+        .filter_map(|(i, planet)| ray_sphere_intersection(&camera_pos, &ray, &planet.position, planet.scale).map(|t| (i, t)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+pub fn render_labels(fb: &mut Framebuffer, planets: &[Planet], camera: &Camera, selected: Option<usize>) {
+    let camera_pos = camera.get_position();
+    let view_proj = camera.view_projection(fb.width, fb.height);
+
+    for (i, planet) in planets.iter().enumerate() {
+        let dist = (planet.position - camera_pos).length();
+        let is_selected = selected == Some(i);
+        // The selected planet stays labelled however far away it is
+        if dist > LABEL_DISTANCE && !is_selected {
+            continue;
+        }
+        let projected = project_vertex(&planet.position, &view_proj, fb.width, fb.height);
+        if let Some((sx, sy, _)) = projected {
+            let screen_radius = planet.scale * fb.height as f32 / (2.0 * dist * (camera.fov / 2.0).tan());
+            let x = sx as i32 - font::text_width(&planet.name) / 2;
+            let y = (sy - screen_radius) as i32 - 20;
+            let color = if is_selected { 0xFFFF60 } else { 0xC0C0C0 };
+            font::draw_text(fb, x, y, &planet.name, color);
+        }
+    }
+}
+
+pub fn render_hud(fb: &mut Framebuffer, spaceship: &Spaceship, system: &SolarSystem, selected: Option<usize>) {
+    let speed = spaceship.velocity.length();
+    let nearest = system.nearest_planet(&spaceship.position);
+    let nearest = (spaceship.position - nearest.position).length() - nearest.scale;
+
+    // Turn the speed readout red once the ship is pinned against the cap
+    let speed_color = if speed >= spaceship.max_speed() * 0.95 { 0xFF4040 } else { 0xFFFFFF };
+
+    let x = 10;
+    let mut y = 10;
+    font::draw_text(fb, x, y, &format!("SPEED    {:.1}", speed), speed_color);
+    if !spaceship.inertial_dampeners {
+        font::draw_text(fb, x + font::text_width("SPEED    00.0  "), y, "COAST", 0x40C0FF);
+    }
+    y += HUD_LINE_HEIGHT;
+    font::draw_text(fb, x, y, &format!("ALTITUDE {:.1}", spaceship.position.y), 0xFFFFFF);
+    y += HUD_LINE_HEIGHT;
+    font::draw_text(fb, x, y, &format!("NEAREST  {:.1}", nearest), 0xFFFFFF);
+    y += HUD_LINE_HEIGHT;
+    let health_color = if spaceship.health < MAX_HEALTH * 0.3 { 0xFF4040 } else { 0xFFFFFF };
+    font::draw_text(fb, x, y, &format!("HULL     {:.0}%", spaceship.health / MAX_HEALTH * 100.0), health_color);
+    y += HUD_LINE_HEIGHT;
+    font::draw_text(fb, x, y, "FUEL", 0xFFFFFF);
+    let fuel_color = if spaceship.fuel < WARP_FUEL_COST { 0xFF4040 } else { 0x40C0FF };
+    draw_bar(fb, x + font::text_width("FUEL     "), y + 2, 120, 10, spaceship.fuel / MAX_FUEL, fuel_color);
+    y += HUD_LINE_HEIGHT;
+    // Heat shades from yellow to red, and the label turns red while locked out
+    let heat_color = Color::new(255, 220, 60).lerp(&Color::new(255, 40, 20), spaceship.heat).to_u32();
+    let label_color = if spaceship.boost_locked { 0xFF4040 } else { 0xFFFFFF };
+    font::draw_text(fb, x, y, if spaceship.boost_locked { "OVERHEAT" } else { "HEAT" }, label_color);
+    draw_bar(fb, x + font::text_width("HEAT     "), y + 2, 120, 10, spaceship.heat, heat_color);
+    y += HUD_LINE_HEIGHT;
+    let explored = format!("EXPLORED {}/{}", spaceship.explored.len(), system.planets.len());
+    font::draw_text(fb, x, y, &explored, 0xFFFFFF);
+    y += HUD_LINE_HEIGHT;
+    font::draw_text(fb, x, y, &format!("ODOMETER {:.0}", spaceship.odometer), 0xFFFFFF);
+    if let Some(planet) = selected.map(|i| &system.planets[i]) {
+        y += HUD_LINE_HEIGHT;
+        let distance = (spaceship.position - planet.position).length() - planet.scale;
+        font::draw_text(fb, x, y, &format!("TARGET   {} {:.1}", planet.name, distance), 0xFFFF60);
+    }
+}
+
+/// Outlined horizontal gauge filled to `fraction` (0..=1).
+fn draw_bar(fb: &mut Framebuffer, x: i32, y: i32, width: i32, height: i32, fraction: f32, color: u32) {
+    let filled = (width as f32 * fraction.clamp(0.0, 1.0)) as i32;
+    for dy in 0..height {
+        for dx in 0..width {
+            let edge = dx == 0 || dx == width - 1 || dy == 0 || dy == height - 1;
+            if edge {
+                fb.set(x + dx, y + dy, 0x808080);
+            } else if dx < filled {
+                fb.set(x + dx, y + dy, color);
+            }
+        }
+    }
+}
+
+fn draw_line_2d(fb: &mut Framebuffer, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let x = x0 + ((x1 - x0) as f32 * t).round() as i32;
+        let y = y0 + ((y1 - y0) as f32 * t).round() as i32;
+        fb.set(x, y, color);
+    }
+}
+
+/// Moving average of recent frame times for the performance readout.
+pub struct FrameTimer {
+    samples: std::collections::VecDeque<f32>,
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        FrameTimer {
+            samples: std::collections::VecDeque::with_capacity(FPS_SAMPLES),
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.samples.len() == FPS_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+    }
+
+    pub fn average(&self) -> f32 {
+        self.samples.iter().sum::<f32>() / self.samples.len().max(1) as f32
+    }
+}
+
+/// FPS and milliseconds per frame in the bottom-right corner.
+pub fn render_frame_stats(fb: &mut Framebuffer, timer: &FrameTimer) {
+    let frame_time = timer.average();
+    let fps = if frame_time > 0.0 { 1.0 / frame_time } else { 0.0 };
+    let text = format!("{:.0} FPS {:.1} MS", fps, frame_time * 1000.0);
+    let x = fb.width as i32 - font::text_width(&text) - 10;
+    let y = fb.height as i32 - HUD_LINE_HEIGHT - 4;
+    font::draw_text(fb, x, y, &text, 0x80FF80);
+}
+
+pub fn render_minimap(fb: &mut Framebuffer, spaceship: &Spaceship, planets: &[Planet]) {
+    let size = 180;
+    let left = fb.width as i32 - size - 10;
+    let top = 10;
+    let center_x = left + size / 2;
+    let center_y = top + size / 2;
+
+    for y in top..top + size {
+        for x in left..left + size {
+            let edge = x == left || x == left + size - 1 || y == top || y == top + size - 1;
+            fb.set(x, y, if edge { 0x606060 } else { 0x080810 });
+        }
+    }
+
+    // Fit the widest orbit (at aphelion) inside the map with a small margin
+    let extent = planets
+        .iter()
+        .map(|p| p.orbit_radius * (1.0 + p.eccentricity) + p.scale)
+        .fold(1.0, f32::max);
+    let scale = (size as f32 / 2.0 - 6.0) / extent;
+    let to_map = |pos: &Vec3| {
+        (
+            center_x + (pos.x * scale) as i32,
+            center_y + (pos.z * scale) as i32,
+        )
+    };
+
+    for planet in planets {
+        let (px, py) = to_map(&planet.position);
+        let radius = ((planet.scale * scale) as i32).max(2);
+        let color = planet.representative_color().to_u32();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (x, y) = (px + dx, py + dy);
+                let inside = x > left && x < left + size - 1 && y > top && y < top + size - 1;
+                if dx * dx + dy * dy <= radius * radius && inside {
+                    fb.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    let (sx, sy) = to_map(&spaceship.position);
+    if sx > left && sx < left + size - 1 && sy > top && sy < top + size - 1 {
+        let forward = spaceship.get_forward();
+        let heading = Vec3::new(forward.x, 0.0, forward.z).normalize();
+        let tip_x = sx + (heading.x * 12.0) as i32;
+        let tip_y = sy + (heading.z * 12.0) as i32;
+        draw_line_2d(fb, sx, sy, tip_x, tip_y, 0x40FF40);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                fb.set(sx + dx, sy + dy, 0xFFFFFF);
+            }
+        }
+    }
+}
+
+/// Radial light streaks rushing past the ship while a warp is in progress.
+pub fn render_warp_streaks(fb: &mut Framebuffer, progress: f32) {
+    let intensity = (progress * PI).sin();
+    let center_x = fb.width as f32 / 2.0;
+    let center_y = fb.height as f32 / 2.0;
+    // Streak lengths are in window pixels
+    let scale = fb.scale as f32;
+    let mut rng = Rng::new(777);
+
+    for _ in 0..160 {
+        let angle = (rng.next_u32() % 3600) as f32 / 3600.0 * 2.0 * PI;
+        let start = (40.0 + (rng.next_u32() % 500) as f32 + progress * 300.0) * scale;
+        let length = (20.0 + 240.0 * intensity) * scale;
+
+        let (dir_x, dir_y) = (angle.cos(), angle.sin());
+        let color = Color::from_float(0.8 * intensity, 0.85 * intensity, intensity).to_u32();
+        draw_line_2d(
+            fb,
+            (center_x + dir_x * start) as i32,
+            (center_y + dir_y * start) as i32,
+            (center_x + dir_x * (start + length)) as i32,
+            (center_y + dir_y * (start + length)) as i32,
+            color,
+        );
+    }
+}
+
+/// The procedural star field: how many stars, how bright, and which sky.
+/// The same seed always places the same stars.
+#[derive(Clone, Copy, Debug)]
+pub struct SkyboxConfig {
+    pub star_count: usize,
+    pub min_brightness: u8,
+    pub max_brightness: u8,
+    pub seed: u64,
+    /// Plain white stars instead of spectral colors
+    pub monochrome: bool,
+    /// Faint clouds behind the stars
+    pub nebula: bool,
+}
+
+impl Default for SkyboxConfig {
+    fn default() -> Self {
+        SkyboxConfig {
+            star_count: STAR_COUNT,
+            min_brightness: 120,
+            max_brightness: 255,
+            seed: 12345,
+            monochrome: false,
+            nebula: false,
+        }
+    }
+}
+
+/// Stars are fixed directions on the unit sphere, projected from the origin
+/// so they turn with the camera but never drift as it moves, as if at infinity.
+pub fn render_skybox(fb: &mut Framebuffer, camera: &Camera, config: &SkyboxConfig, time: f32) {
+    let mut rng = Rng::from_seed(config.seed);
+    let (min_brightness, max_brightness) = (config.min_brightness, config.max_brightness.max(config.min_brightness));
+    let brightness_span = (max_brightness - min_brightness) as u32 + 1;
+    if config.nebula {
+        render_nebula(fb, camera);
+    }
+    
+    // Stars are infinitely far away, so only the camera's rotation applies
+    let origin = Vec3::new(0.0, 0.0, 0.0);
+    let view = Mat4::look_at(&origin, &camera.get_forward(), &Vec3::new(0.0, 1.0, 0.0));
+    let sky_proj = camera.projection(fb.width, fb.height) * view;
+    
+    for i in 0..config.star_count {
+        // Uniform on the sphere: uniform height and uniform angle around it
+        let y = rng.range(-1.0, 1.0);
+        let angle = rng.range(0.0, 2.0 * PI);
+        let ring = (1.0 - y * y).sqrt();
+        let direction = Vec3::new(ring * angle.cos(), y, ring * angle.sin());
+        let brightness = min_brightness + (rng.next_u32() % brightness_span) as u8;
+        let twinkle = 1.0 - TWINKLE_DEPTH * (0.5 + 0.5 * (time * TWINKLE_SPEED + i as f32 * TWINKLE_PHASE_STEP).sin());
+        let brightness = (brightness as f32 * twinkle) as u8;
+        // Drawn even when monochrome so the toggle keeps the same sky
+        let tint = spectral_tint(rng.next_f32());
+        let tint = if config.monochrome { WHITE_LIGHT } else { tint };
+        
+        let Some((sx, sy, _)) = project_vertex(&direction, &sky_proj, fb.width, fb.height) else {
+            continue;
+        };
+        
+        let (x, y) = (sx as i32, sy as i32);
+        if !fb.in_bounds(x, y) {
+            continue;
+        }
+        
+        // Stars keep their window size when supersampling
+        let scale = fb.scale as i32;
+        let (x, y) = (x - x % scale, y - y % scale);
+        let color = tint.filter(&Color::new(brightness, brightness, brightness)).to_u32();
+        for sy in 0..scale {
+            for sx in 0..scale {
+                fb.set(x + sx, y + sy, color);
+            }
+        }
+    }
+}
+
+/// Fills the buffer with low-contrast clouds from fbm over each pixel's
+/// view direction, so they turn with the camera like the stars do. They're
+/// soft enough that a coarse grid of samples, blended, looks the same as
+/// shading every pixel.
+fn render_nebula(fb: &mut Framebuffer, camera: &Camera) {
+    let forward = camera.get_forward();
+    let right = camera.get_right();
+    let up = right.cross(&forward).normalize();
+    let (width, height) = (fb.width as f32, fb.height as f32);
+    let fov_factor = (camera.fov / 2.0).tan();
+    let aspect = width / height;
+    // Inverse of project_vertex, as in render_sphere
+    let ray = |x: f32, y: f32| {
+        let ndc_x = (x + 0.5) / (width / 2.0) - 1.0;
+        let ndc_y = 1.0 - (y + 0.5) / (height / 2.0);
+        (forward + right * (ndc_x * fov_factor * aspect) + up * (ndc_y * fov_factor)).normalize()
+    };
+
+    let spacing = NEBULA_SAMPLE_SPACING * fb.scale;
+    let (columns, rows) = (fb.width / spacing + 2, fb.height / spacing + 2);
+    let samples: Vec<Vec3> = (0..rows * columns)
+        .map(|i| nebula_color(&ray(((i % columns) * spacing) as f32, ((i / columns) * spacing) as f32)))
+        .collect();
+
+    for y in 0..fb.height {
+        let (row, v) = (y / spacing, (y % spacing) as f32 / spacing as f32);
+        for x in 0..fb.width {
+            let (column, u) = (x / spacing, (x % spacing) as f32 / spacing as f32);
+            let at = |c: usize, r: usize| samples[r * columns + c];
+            let top = at(column, row) * (1.0 - u) + at(column + 1, row) * u;
+            let bottom = at(column, row + 1) * (1.0 - u) + at(column + 1, row + 1) * u;
+            let color = top * (1.0 - v) + bottom * v;
+            fb.color[y * fb.width + x] = Color::from_float(color.x, color.y, color.z).to_u32();
+        }
+    }
+}
+
+/// Nebula color looking along `direction`, as 0..1 RGB in a `Vec3` so it
+/// can be interpolated.
+fn nebula_color(direction: &Vec3) -> Vec3 {
+    let density = fbm(&(*direction * NEBULA_SCALE), 4);
+    let density = ((density - NEBULA_THRESHOLD) / (NEBULA_FULL - NEBULA_THRESHOLD)).clamp(0.0, 1.0);
+    // A second, broader pattern drifts the hue between the two colors
+    let hue = fbm(&(*direction * (NEBULA_SCALE * 0.5) + Vec3::new(17.0, 3.0, 9.0)), 2);
+    let tint = NEBULA_COLOR_A.lerp(&NEBULA_COLOR_B, hue * 1.5 - 0.25);
+    let strength = density * density * NEBULA_BRIGHTNESS / 255.0;
+    Vec3::new(tint.r as f32, tint.g as f32, tint.b as f32) * strength
+}
+
+/// Star color for a uniform `roll` in [0, 1), picking a spectral class by
+/// its share of the field.
+fn spectral_tint(roll: f32) -> Color {
+    let mut remaining = roll;
+    for &(share, tint) in &SPECTRAL_CLASSES {
+        if remaining < share {
+            return tint;
+        }
+        remaining -= share;
+    }
+    SPECTRAL_CLASSES[SPECTRAL_CLASSES.len() - 1].1
+}
+
+/// Six images on the inside of a cube around the camera: +X, -X, +Y, -Y,
+/// +Z and -Z, in the usual cubemap orientation.
+pub struct Skybox {
+    /// Always six, in that order
+    faces: Vec<Texture>,
+}
+
+impl Skybox {
+    /// Loads `px`, `nx`, `py`, `ny`, `pz` and `nz` from `dir`, each as a
+    /// .png or a .ppm.
+    pub fn load(dir: &str) -> std::io::Result<Skybox> {
+        let faces = ["px", "nx", "py", "ny", "pz", "nz"]
+            .iter()
+            .map(|name| {
+                let png = format!("{}/{}.png", dir, name);
+                if std::path::Path::new(&png).exists() {
+                    Texture::load(&png)
+                } else {
+                    Texture::load(&format!("{}/{}.ppm", dir, name))
+                }
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Skybox { faces })
+    }
+
+    /// The color seen looking along `direction`, which needn't be unit length.
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        // The dominant axis picks the face; the other two span it from -1 to 1
+        let (face, u, v) = if ax >= ay && ax >= az {
+            if x > 0.0 { (0, -z / ax, -y / ax) } else { (1, z / ax, -y / ax) }
+        } else if ay >= az {
+            if y > 0.0 { (2, x / ay, z / ay) } else { (3, x / ay, -z / ay) }
+        } else if z > 0.0 {
+            (4, x / az, -y / az)
+        } else {
+            (5, -x / az, -y / az)
+        };
+        self.faces[face].sample_clamped((u + 1.0) / 2.0, (v + 1.0) / 2.0)
+    }
+}
+
+/// Fills the whole buffer from `skybox`, tracing each pixel's view ray.
+/// Like the stars it sits at infinity, so only the camera's rotation counts.
+pub fn render_skybox_cubemap(fb: &mut Framebuffer, camera: &Camera, skybox: &Skybox) {
+    let forward = camera.get_forward();
+    let right = camera.get_right();
+    let up = right.cross(&forward).normalize();
+    let (width, height) = (fb.width as f32, fb.height as f32);
+    let fov_factor = (camera.fov / 2.0).tan();
+    let aspect = width / height;
+
+    for y in 0..fb.height {
+        // Inverse of project_vertex, as in render_sphere
+        let ndc_y = 1.0 - (y as f32 + 0.5) / (height / 2.0);
+        for x in 0..fb.width {
+            let ndc_x = (x as f32 + 0.5) / (width / 2.0) - 1.0;
+            let ray = forward + right * (ndc_x * fov_factor * aspect) + up * (ndc_y * fov_factor);
+            fb.color[y * fb.width + x] = skybox.sample(&ray).to_u32();
+        }
+    }
+}
+
+/// A sun as a light source. Its sphere is still one of the planets, so it
+/// collides, pulls and shows on the minimap like any other body; the star
+/// adds the color of its light and, in a binary, its circle around the
+/// barycenter that sphere's own orbit follows.
+pub struct Star {
+    /// Index of the star's sphere in `SolarSystem::planets`
+    pub body: usize,
+    pub light: Color,
+    /// Distance from the barycenter; zero for a lone sun sitting on it
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub orbit_angle: f32,
+}
+
+impl Star {
+    fn offset(&self) -> Vec3 {
+        Vec3::new(self.orbit_angle.cos(), 0.0, self.orbit_angle.sin()) * self.orbit_radius
+    }
+
+    fn velocity(&self) -> Vec3 {
+        Vec3::new(-self.orbit_angle.sin(), 0.0, self.orbit_angle.cos()) * (self.orbit_radius * self.orbit_speed)
+    }
+}
+
+/// Every planet in the scene, with the sun first. Never empty.
+pub struct SolarSystem {
+    pub planets: Vec<Planet>,
+    /// One per sun-shaded planet, plus whatever `make_binary` adds
+    pub stars: Vec<Star>,
+    /// Where each planet is, for collision and gravity queries. Call
+    /// `planets_moved` after moving planets outside of `update`.
+    pub grid: SpatialGrid,
+    /// Gravitational constant between planets once `enable_n_body` has
+    /// switched them off their kinematic orbits
+    n_body_g: Option<f32>,
+    /// Seconds not yet covered by a whole N-body step
+    n_body_time: f32,
+}
+
+impl SolarSystem {
+    pub fn built_in() -> Self {
+        let planets = vec![
+            Planet::builder()
+                .name("Sun")
+                .spin(0.05, 0.0)
+                .scale(5.0)
+                .shader(ShaderType::Sun)
+                .build(),
+            Planet::builder()
+                .name("Terra")
+                .orbit(20.0, 0.3)
+                .inclination(0.03)
+                .spin(0.5, 0.41)
+                .scale(2.0)
+                .shader(ShaderType::Earth)
+                .add_moon(Moon { orbit_radius: 4.0, orbit_speed: 2.0, size: 0.5, angle: 0.0 })
+                .build(),
+            Planet::builder()
+                .name("Gigas")
+                .orbit(35.0, 0.2)
+                .inclination(0.05)
+                .orbit_angle(1.5)
+                .spin(0.3, 0.1)
+                .scale(4.0)
+                .shader(ShaderType::GasGiant)
+                .with_ring(Color::new(200, 170, 130))
+                .add_moon(Moon { orbit_radius: 7.0, orbit_speed: 1.5, size: 0.8, angle: 0.0 })
+                .add_moon(Moon { orbit_radius: 9.0, orbit_speed: 1.2, size: 0.6, angle: PI })
+                .build(),
+            Planet::builder()
+                .name("Glacies")
+                .orbit(50.0, 0.15)
+                .eccentricity(0.05)
+                .inclination(-0.04)
+                .orbit_angle(3.0)
+                .spin(0.4, 0.2)
+                .scale(3.0)
+                .shader(ShaderType::Ice)
+                .build(),
+            Planet::builder()
+                .name("Duna")
+                .orbit(65.0, 0.12)
+                .inclination(0.07)
+                .orbit_angle(4.5)
+                .spin(0.6, 0.44)
+                .scale(2.5)
+                .shader(ShaderType::Desert)
+                .add_moon(Moon { orbit_radius: 5.0, orbit_speed: 1.8, size: 0.6, angle: PI / 2.0 })
+                .build(),
+            Planet::builder()
+                .name("Vulcan")
+                .orbit(80.0, 0.1)
+                .eccentricity(0.12)
+                .inclination(-0.06)
+                .orbit_angle(5.5)
+                .spin(0.35, 0.1)
+                .scale(2.8)
+                .shader(ShaderType::Lava)
+                .add_moon(Moon { orbit_radius: 5.5, orbit_speed: 2.0, size: 0.7, angle: 0.0 })
+                .build(),
+            Planet::builder()
+                .name("Viola")
+                .orbit(95.0, 0.08)
+                .inclination(0.09)
+                .orbit_angle(0.5)
+                .spin(0.25, 0.5)
+                .scale(3.5)
+                .shader(ShaderType::Purple)
+                .with_ring(Color::new(140, 100, 180))
+                .add_moon(Moon { orbit_radius: 6.0, orbit_speed: 1.6, size: 0.5, angle: 0.0 })
+                .add_moon(Moon { orbit_radius: 8.5, orbit_speed: 1.1, size: 0.7, angle: PI / 3.0 })
+                .build(),
+        ];
+        SolarSystem::new(planets)
+    }
+
+    /// Loads planets from a JSON file shaped like:
+    ///
+    /// ```json
+    /// { "planets": [ { "shader": "earth", "orbit_radius": 20, "orbit_speed": 0.3,
+    ///                  "scale": 2, "rotation_speed": 0.5,
+    ///                  "moons": [ { "orbit_radius": 4, "orbit_speed": 2, "size": 0.5 } ] } ] }
+    /// ```
+    ///
+    /// `shader`, `orbit_radius` and `scale` are required; every other field
+    /// defaults to zero, `has_rings` to false, `ring_color` to `[0, 0, 0]` and
+    /// `name` to the shader name. `base_color_a` and `base_color_b` take
+    /// `[r, g, b]` and replace the shader's palette when given.
+    pub fn from_json(path: &str) -> std::io::Result<Self> {
+        let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+        let contents = std::fs::read_to_string(path)?;
+        let root = json::parse(&contents).map_err(invalid)?;
+        let entries = root
+            .get("planets")
+            .and_then(|p| p.as_array())
+            .ok_or_else(|| invalid("missing \"planets\" array".to_string()))?;
+        if entries.is_empty() {
+            return Err(invalid("\"planets\" array is empty".to_string()));
+        }
+
+        let planets = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| planet_from_json(entry).map_err(|e| invalid(format!("planet {}: {}", i, e))))
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        Ok(SolarSystem::new(planets))
+    }
+
+    /// Writes the system in the format `from_json` reads, including the
+    /// current orbit, spin and moon angles so a shared file starts where
+    /// this one was.
+    pub fn to_json(&self, path: &str) -> std::io::Result<()> {
+        use std::io::Write;
+
+        let rgb = |c: Color| format!("[{}, {}, {}]", c.r, c.g, c.b);
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"planets\": [")?;
+        for (i, planet) in self.planets.iter().enumerate() {
+            writeln!(writer, "    {{")?;
+            writeln!(writer, "      \"name\": {},", json::quote(&planet.name))?;
+            writeln!(writer, "      \"shader\": \"{}\",", planet.shader.name())?;
+            writeln!(writer, "      \"orbit_radius\": {},", planet.orbit_radius)?;
+            writeln!(writer, "      \"orbit_speed\": {},", planet.orbit_speed)?;
+            writeln!(writer, "      \"eccentricity\": {},", planet.eccentricity)?;
+            writeln!(writer, "      \"inclination\": {},", planet.inclination)?;
+            writeln!(writer, "      \"rotation_speed\": {},", planet.rotation_speed)?;
+            writeln!(writer, "      \"axial_tilt\": {},", planet.axial_tilt)?;
+            writeln!(writer, "      \"scale\": {},", planet.scale)?;
+            writeln!(writer, "      \"rotation\": {},", planet.rotation)?;
+            writeln!(writer, "      \"orbit_angle\": {},", planet.orbit_angle)?;
+            writeln!(writer, "      \"has_rings\": {},", planet.has_rings)?;
+            writeln!(writer, "      \"ring_color\": {},", rgb(planet.ring_color))?;
+            if let Some(color) = planet.base_color_a {
+                writeln!(writer, "      \"base_color_a\": {},", rgb(color))?;
+            }
+            if let Some(color) = planet.base_color_b {
+                writeln!(writer, "      \"base_color_b\": {},", rgb(color))?;
+            }
+            if let Some(texture) = &planet.texture {
+                writeln!(writer, "      \"texture\": {},", json::quote(&texture.path))?;
+            }
+            write!(writer, "      \"moons\": [")?;
+            for (j, moon) in planet.moons.iter().enumerate() {
+                let separator = if j == 0 { "\n" } else { ",\n" };
+                write!(
+                    writer,
+                    "{}        {{ \"orbit_radius\": {}, \"orbit_speed\": {}, \"size\": {}, \"angle\": {} }}",
+                    separator, moon.orbit_radius, moon.orbit_speed, moon.size, moon.angle
+                )?;
+            }
+            if planet.moons.is_empty() {
+                writeln!(writer, "]")?;
+            } else {
+                writeln!(writer, "\n      ]")?;
+            }
+            writeln!(writer, "    }}{}", if i + 1 < self.planets.len() { "," } else { "" })?;
+        }
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+}
+
+impl SolarSystem {
+    /// Builds a random but reproducible system: the same seed always gives
+    /// the same planets, moons and rings.
+    pub fn generate(seed: u64) -> Self {
+        const SYLLABLES: [&str; 12] = ["ka", "lo", "ri", "ven", "tor", "sa", "mi", "dra", "xe", "nu", "bel", "os"];
+        const SHADERS: [ShaderType; 9] = [
+            ShaderType::Earth,
+            ShaderType::GasGiant,
+            ShaderType::Ice,
+            ShaderType::Desert,
+            ShaderType::Lava,
+            ShaderType::Purple,
+            ShaderType::Ocean,
+            ShaderType::Toxic,
+            ShaderType::Crystal,
+        ];
+
+        let mut rng = Rng::from_seed(seed);
+        let mut planets = SolarSystem::built_in().planets;
+        planets.truncate(1); // Keep the sun
+
+        let count = rng.range_u32(3, 8);
+        let mut orbit_radius = 10.0;
+        for _ in 0..count {
+            orbit_radius += rng.range(10.0, 18.0);
+            let mut name: String = (0..rng.range_u32(2, 3))
+                .map(|_| SYLLABLES[rng.range_u32(0, SYLLABLES.len() as u32 - 1) as usize])
+                .collect();
+            name[..1].make_ascii_uppercase();
+
+            let shader = SHADERS[rng.range_u32(0, SHADERS.len() as u32 - 1) as usize];
+            let scale = if shader == ShaderType::GasGiant { rng.range(3.0, 4.5) } else { rng.range(1.5, 3.2) };
+            let has_rings = rng.next_f32() < if shader == ShaderType::GasGiant { 0.6 } else { 0.15 };
+            let ring_color = Color::new(
+                rng.range(120.0, 220.0) as u8,
+                rng.range(100.0, 190.0) as u8,
+                rng.range(90.0, 200.0) as u8,
+            );
+
+            let moons: Vec<Moon> = (0..rng.range_u32(0, 3))
+                .map(|i| Moon {
+                    orbit_radius: scale + 2.0 + i as f32 * 2.0 + rng.range(0.0, 1.0),
+                    orbit_speed: rng.range(1.0, 2.2),
+                    size: rng.range(0.3, 0.8),
+                    angle: rng.next_f32() * 2.0 * PI,
+                })
+                .collect();
+
+            let orbit_angle = rng.next_f32() * 2.0 * PI;
+            let eccentricity = rng.range(0.0, 0.12);
+            let inclination = rng.range(-0.1, 0.1);
+            // Roughly Keplerian: outer planets crawl
+            let orbit_speed = 0.3 * (20.0 / orbit_radius).powf(1.5) * rng.range(0.8, 1.2);
+            let mut planet = Planet::builder()
+                .name(&name)
+                .orbit(orbit_radius, orbit_speed)
+                .eccentricity(eccentricity)
+                .inclination(inclination)
+                .orbit_angle(orbit_angle)
+                .spin(rng.range(0.2, 0.8), rng.range(0.0, 0.5))
+                .scale(scale)
+                .shader(shader);
+            if has_rings {
+                planet = planet.with_ring(ring_color);
+            }
+            for moon in moons {
+                planet = planet.add_moon(moon);
+            }
+            planets.push(planet.build());
+        }
+
+        SolarSystem::new(planets)
+    }
+}
+
+impl SolarSystem {
+    pub fn new(planets: Vec<Planet>) -> Self {
+        let grid = SpatialGrid::new(SPATIAL_CELL_SIZE, &planets);
+        let stars = (0..planets.len())
+            .filter(|&i| planets[i].shader == ShaderType::Sun)
+            .map(|body| Star { body, light: WHITE_LIGHT, orbit_radius: 0.0, orbit_speed: 0.0, orbit_angle: 0.0 })
+            .collect();
+        SolarSystem { planets, stars, grid, n_body_g: None, n_body_time: 0.0 }
+    }
+
+    /// Splits the first star into a close binary: a warm primary and a
+    /// smaller blue companion circling their common barycenter where the
+    /// sun used to be, so the planets now orbit the pair. The companion is
+    /// added at the end of the planet list. Returns false, changing
+    /// nothing, if there's no star to split.
+    pub fn make_binary(&mut self) -> bool {
+        let Some(primary) = self.stars.first_mut() else {
+            return false;
+        };
+        let sun = &mut self.planets[primary.body];
+        sun.scale = BINARY_PRIMARY_SCALE;
+        let mut companion = Planet::builder()
+            .name(&format!("{} B", sun.name))
+            .orbit(sun.orbit_radius, sun.orbit_speed)
+            .eccentricity(sun.eccentricity)
+            .inclination(sun.inclination)
+            .orbit_angle(sun.orbit_angle)
+            .spin(sun.rotation_speed, sun.axial_tilt)
+            .scale(BINARY_COMPANION_SCALE)
+            .shader(ShaderType::Sun)
+            .base_colors(Some(Color::new(215, 230, 255)), Some(Color::new(120, 160, 255)))
+            .build();
+
+        // Each star's distance from the barycenter goes inversely with its mass
+        let (primary_mass, companion_mass) = (sun.mass(), companion.mass());
+        let total = primary_mass + companion_mass;
+        primary.light = BINARY_PRIMARY_LIGHT;
+        primary.orbit_radius = BINARY_SEPARATION * companion_mass / total;
+        primary.orbit_speed = BINARY_ORBIT_SPEED;
+        let secondary = Star {
+            body: self.planets.len(),
+            light: BINARY_COMPANION_LIGHT,
+            orbit_radius: BINARY_SEPARATION * primary_mass / total,
+            orbit_speed: BINARY_ORBIT_SPEED,
+            orbit_angle: primary.orbit_angle + PI,
+        };
+        companion.update(0.0);
+        self.planets.push(companion);
+        self.stars.push(secondary);
+        self.place_stars();
+        self.rebuild_grid();
+        true
+    }
+
+    /// Moves each orbiting star's sphere out from the barycenter, which is
+    /// wherever that sphere's own orbit puts it.
+    fn place_stars(&mut self) {
+        for star in self.stars.iter().filter(|star| star.orbit_radius > 0.0) {
+            let body = &mut self.planets[star.body];
+            body.update(0.0);
+            body.position = body.position + star.offset();
+            body.velocity = body.velocity + star.velocity();
+        }
+    }
+
+    /// The bodies everything else orbits: the stars, or failing any, the
+    /// first planet.
+    fn central_bodies(&self) -> Vec<usize> {
+        if self.stars.is_empty() {
+            vec![0]
+        } else {
+            self.stars.iter().map(|star| star.body).collect()
+        }
+    }
+
+    /// Total mass of `bodies` and their center of mass.
+    fn barycenter(&self, bodies: &[usize]) -> (f32, Vec3) {
+        let total: f32 = bodies.iter().map(|&i| self.planets[i].mass()).sum();
+        let weighted = bodies
+            .iter()
+            .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &i| sum + self.planets[i].position * self.planets[i].mass());
+        (total, weighted * (1.0 / total))
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        match self.n_body_g {
+            None => {
+                for planet in &mut self.planets {
+                    planet.update(dt);
+                }
+                for star in &mut self.stars {
+                    star.orbit_angle += star.orbit_speed * dt;
+                }
+                self.place_stars();
+            }
+            Some(g) => {
+                // Fixed steps keep the integration the same at any frame rate
+                self.n_body_time += dt;
+                while self.n_body_time >= N_BODY_STEP {
+                    self.step_n_body(g, N_BODY_STEP);
+                    self.n_body_time -= N_BODY_STEP;
+                }
+                let (_, center) = self.barycenter(&self.central_bodies());
+                for planet in &mut self.planets {
+                    planet.update_spin(dt);
+                    planet.sync_orbit_angle(center);
+                }
+            }
+        }
+        self.rebuild_grid();
+    }
+
+    /// Call after changing planet positions outside of `update`, e.g. when
+    /// loading a save. In N-body mode the planets set off again on fresh
+    /// orbits from where they now are.
+    pub fn planets_moved(&mut self) {
+        self.place_stars();
+        if let Some(g) = self.n_body_g {
+            self.launch_n_body(g);
+        }
+        self.rebuild_grid();
+    }
+
+    fn rebuild_grid(&mut self) {
+        self.grid.rebuild(&self.planets);
+    }
+
+    /// Switches from kinematic orbits to N-body gravity, where every planet
+    /// pulls on every other and nothing follows `orbit_angle` any more.
+    /// `mu` is the stars' combined gravitational parameter, as for
+    /// `apply_kepler`, so orbits near the sun keep similar periods.
+    /// Kinematic orbits are the default because this one can drift: close
+    /// encounters swap energy between planets and nothing pulls them back
+    /// onto their ellipses.
+    pub fn enable_n_body(&mut self, mu: f32) {
+        let (star_mass, _) = self.barycenter(&self.central_bodies());
+        let g = mu / star_mass;
+        self.n_body_g = Some(g);
+        self.n_body_time = 0.0;
+        self.launch_n_body(g);
+        self.rebuild_grid();
+    }
+
+    /// Starts each orbiting planet along its orbit at the vis-viva speed for
+    /// its distance from the stars, and a binary's stars on circles around
+    /// each other. The stars then take up the opposite of everything's
+    /// momentum so the system as a whole stays put.
+    fn launch_n_body(&mut self, g: f32) {
+        let centers = self.central_bodies();
+        let (star_mass, barycenter) = self.barycenter(&centers);
+        let mu = g * star_mass;
+        for (i, planet) in self.planets.iter_mut().enumerate() {
+            if centers.contains(&i) || planet.orbit_radius <= 0.0 {
+                planet.velocity = Vec3::new(0.0, 0.0, 0.0);
+                continue;
+            }
+            let distance = (planet.position - barycenter).length();
+            let speed = (mu * (2.0 / distance - 1.0 / planet.orbit_radius)).max(0.0).sqrt();
+            let direction = planet.orbit_tangent().normalize();
+            // Backwards orbits stay backwards
+            let direction = if planet.orbit_speed < 0.0 { -direction } else { direction };
+            planet.velocity = direction * speed;
+        }
+        for star in self.stars.iter().filter(|star| star.orbit_radius > 0.0) {
+            let body = &mut self.planets[star.body];
+            // Two-body circular orbit: both stars share the angular speed
+            // set by their separation
+            let separation = star.orbit_radius * star_mass / (star_mass - body.mass());
+            let angular_speed = (g * star_mass / separation.powi(3)).sqrt();
+            body.velocity = star.velocity().normalize() * (star.orbit_radius * angular_speed);
+        }
+
+        let momentum = self
+            .planets
+            .iter()
+            .fold(Vec3::new(0.0, 0.0, 0.0), |sum, planet| sum + planet.velocity * planet.mass());
+        let correction = momentum * (1.0 / star_mass);
+        for &i in &centers {
+            self.planets[i].velocity = self.planets[i].velocity - correction;
+        }
+    }
+
+    /// One velocity Verlet step: move by the current velocity and
+    /// acceleration, then update the velocity with the average of the old
+    /// and new accelerations.
+    fn step_n_body(&mut self, g: f32, h: f32) {
+        let accelerations = n_body_accelerations(&self.planets, g);
+        for (planet, a) in self.planets.iter_mut().zip(&accelerations) {
+            planet.position = planet.position + planet.velocity * h + *a * (0.5 * h * h);
+        }
+        let next = n_body_accelerations(&self.planets, g);
+        for ((planet, a), a_next) in self.planets.iter_mut().zip(&accelerations).zip(&next) {
+            planet.velocity = planet.velocity + (*a + *a_next) * (0.5 * h);
+        }
+    }
+
+    /// Draws every planet with its rings and moons. Each planet can be
+    /// eclipsed by its own moons and by any nearby planet.
+    pub fn render(&self, fb: &mut Framebuffer, camera: &Camera, time: f32) {
+        let lights = scene_lights(self);
+        for (i, planet) in self.planets.iter().enumerate() {
+            let neighbors = self.planets.iter().enumerate().filter(|&(j, other)| {
+                j != i && other.shader != ShaderType::Sun && (other.position - planet.position).length() < ECLIPSE_RANGE
+            });
+            let occluders: Vec<(Vec3, f32)> = planet
+                .moons
+                .iter()
+                .map(|moon| (moon.get_position(&planet.position), moon.size))
+                .chain(neighbors.map(|(_, other)| (other.position, other.scale)))
+                .collect();
+            render_sphere(
+                fb,
+                &planet.position,
+                planet.scale,
+                planet.shader,
+                planet.rotation,
+                planet.axial_tilt,
+                camera,
+                time,
+                (planet.base_color_a, planet.base_color_b),
+                planet.texture.as_ref(),
+                &lights,
+                &occluders,
+            );
+
+            if planet.has_rings {
+                render_rings(
+                    fb,
+                    &planet.position,
+                    planet.scale * 1.4,
+                    planet.scale * 2.3,
+                    planet.ring_color,
+                    planet.axial_tilt,
+                    camera,
+                );
+            }
+
+            for moon in &planet.moons {
+                let moon_pos = moon.get_position(&planet.position);
+                render_sphere(
+                    fb,
+                    &moon_pos,
+                    moon.size,
+                    ShaderType::Moon, // Gray rocky moons
+                    moon.self_rotation(),
+                    0.0,
+                    camera,
+                    time,
+                    (None, None),
+                    None,
+                    &lights,
+                    &[(planet.position, planet.scale)],
+                );
+            }
+        }
+    }
+
+    /// Replaces every orbit speed with Kepler's third law, so the period
+    /// grows as radius^1.5 and inner planets overtake outer ones.
+    pub fn apply_kepler(&mut self, mu: f32) {
+        for planet in self.planets.iter_mut().filter(|p| p.orbit_radius > 0.0) {
+            planet.orbit_speed = (mu / planet.orbit_radius.powi(3)).sqrt();
+        }
+    }
+
+    /// The planet whose surface is closest to `pos`.
+    pub fn nearest_planet(&self, pos: &Vec3) -> &Planet {
+        &self.planets[self.nearest_planet_index(pos)]
+    }
+
+    pub fn nearest_planet_index(&self, pos: &Vec3) -> usize {
+        let gap = |p: &Planet| (*pos - p.position).length() - p.scale;
+        (0..self.planets.len())
+            .min_by(|&a, &b| gap(&self.planets[a]).total_cmp(&gap(&self.planets[b])))
+            .expect("a solar system always has planets")
+    }
+}
+
+/// Each planet's acceleration from the pull of all the others.
+fn n_body_accelerations(planets: &[Planet], g: f32) -> Vec<Vec3> {
+    planets
+        .iter()
+        .enumerate()
+        .map(|(i, planet)| {
+            planets
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(Vec3::new(0.0, 0.0, 0.0), |total, (_, other)| {
+                    let offset = other.position - planet.position;
+                    let d2 = offset.dot(&offset) + N_BODY_SOFTENING * N_BODY_SOFTENING;
+                    total + offset * (g * other.mass() / (d2 * d2.sqrt()))
+                })
+        })
+        .collect()
+}
+
+fn json_f32(value: &json::JsonValue, key: &str) -> Result<f32, String> {
+    match value.get(key) {
+        Some(v) => v.as_f32().ok_or_else(|| format!("`{}` must be a number", key)),
+        None => Ok(0.0),
+    }
+}
+
+fn required_f32(value: &json::JsonValue, key: &str) -> Result<f32, String> {
+    value
+        .get(key)
+        .ok_or_else(|| format!("missing `{}`", key))?
+        .as_f32()
+        .ok_or_else(|| format!("`{}` must be a number", key))
+}
+
+fn json_color(value: &json::JsonValue, key: &str) -> Result<Option<Color>, String> {
+    match value.get(key).map(|c| c.as_array()) {
+        Some(Some([r, g, b])) => match (r.as_f32(), g.as_f32(), b.as_f32()) {
+            (Some(r), Some(g), Some(b)) => Ok(Some(Color::new(r as u8, g as u8, b as u8))),
+            _ => Err(format!("`{}` must be [r, g, b]", key)),
+        },
+        Some(_) => Err(format!("`{}` must be [r, g, b]", key)),
+        None => Ok(None),
+    }
+}
+
+fn planet_from_json(value: &json::JsonValue) -> Result<Planet, String> {
+    let shader_name = value
+        .get("shader")
+        .and_then(|s| s.as_str())
+        .ok_or("missing `shader`")?;
+    let shader = ShaderType::from_name(shader_name)
+        .ok_or_else(|| format!("unknown shader \"{}\"", shader_name))?;
+
+    let name = value.get("name").and_then(|n| n.as_str()).unwrap_or(shader_name);
+    let mut planet = Planet::builder()
+        .name(name)
+        .orbit(required_f32(value, "orbit_radius")?, json_f32(value, "orbit_speed")?)
+        .eccentricity(json_f32(value, "eccentricity")?)
+        .inclination(json_f32(value, "inclination")?)
+        .orbit_angle(json_f32(value, "orbit_angle")?)
+        .spin(json_f32(value, "rotation_speed")?, json_f32(value, "axial_tilt")?)
+        .rotation(json_f32(value, "rotation")?)
+        .scale(required_f32(value, "scale")?)
+        .shader(shader)
+        .base_colors(json_color(value, "base_color_a")?, json_color(value, "base_color_b")?);
+    // A missing or broken texture only costs the planet its image
+    if let Some(path) = value.get("texture") {
+        let path = path.as_str().ok_or("`texture` must be a string")?;
+        match Texture::load(path) {
+            Ok(texture) => planet = planet.texture(texture),
+            Err(err) => eprintln!("Could not load texture '{}' ({}), using procedural shading", path, err),
+        }
+    }
+    if value.get("has_rings").and_then(|b| b.as_bool()).unwrap_or(false) {
+        planet = planet.with_ring(json_color(value, "ring_color")?.unwrap_or(Color::new(0, 0, 0)));
+    }
+
+    if let Some(entries) = value.get("moons") {
+        let entries = entries.as_array().ok_or("`moons` must be an array")?;
+        for (i, moon) in entries.iter().enumerate() {
+            let with_context = |e: String| format!("moon {}: {}", i, e);
+            planet = planet.add_moon(Moon {
+                orbit_radius: required_f32(moon, "orbit_radius").map_err(with_context)?,
+                orbit_speed: json_f32(moon, "orbit_speed").map_err(with_context)?,
+                size: required_f32(moon, "size").map_err(with_context)?,
+                angle: json_f32(moon, "angle").map_err(with_context)?,
+            });
+        }
+    }
+
+    Ok(planet.build())
+}
+
+/// Everything `Renderer::render_frame` draws, borrowed for one frame.
+pub struct Scene<'a> {
+    pub system: &'a SolarSystem,
+    pub asteroids: &'a [Asteroid],
+    pub spaceship: &'a Spaceship,
+    pub exhaust: &'a ExhaustTrail,
+    pub camera: &'a Camera,
+    pub time: f32,
+}
+
+/// Owns the render targets and the display toggles. The scene renders into
+/// `hires` when supersampling, then gets resolved down into `frame`;
+/// overlays like the HUD draw straight into `frame` afterwards.
+pub struct Renderer {
+    pub frame: Framebuffer,
+    hires: Framebuffer,
+    pub supersampling: bool,
+    pub show_orbits: bool,
+    pub show_grid: bool,
+    /// Overlay where the ship will coast if left alone
+    pub show_trajectory: bool,
+    /// Debug overlay of each planet's spin axis
+    pub show_spin_axes: bool,
+    pub antialiased_lines: bool,
+    pub mesh_shading: MeshShading,
+    /// Skip solid mesh triangles that face away from the camera
+    pub backface_culling: bool,
+    pub star_field: SkyboxConfig,
+    /// Display curve run over the finished scene, before any overlays
+    pub tone_mapping: ToneMapping,
+    pub tone_mapping_enabled: bool,
+    /// How dark the corners get when the vignette is on, 0..=1
+    pub vignette_strength: f32,
+    pub vignette_enabled: bool,
+    /// How far apart red and blue split at the edges with chromatic
+    /// aberration on, 0..=1
+    pub aberration_strength: f32,
+    pub aberration_enabled: bool,
+    /// Copy of the frame for passes that must read the unmodified image
+    scratch: Vec<u32>,
+    /// Drawn behind everything in place of the star field when set
+    pub skybox: Option<Skybox>,
+}
+
+impl Renderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Renderer {
+            frame: Framebuffer::new(width, height),
+            hires: Framebuffer::supersampled(width, height, SUPERSAMPLE_FACTOR),
+            supersampling: false,
+            show_orbits: true,
+            show_grid: false,
+            show_trajectory: false,
+            show_spin_axes: false,
+            antialiased_lines: false,
+            mesh_shading: MeshShading::Wireframe,
+            backface_culling: true,
+            star_field: SkyboxConfig::default(),
+            tone_mapping: ToneMapping::default(),
+            tone_mapping_enabled: false,
+            vignette_strength: DEFAULT_VIGNETTE_STRENGTH,
+            vignette_enabled: false,
+            aberration_strength: DEFAULT_ABERRATION_STRENGTH,
+            aberration_enabled: false,
+            scratch: Vec::new(),
+            skybox: None,
+        }
+    }
+
+    /// Reallocates the targets to a new window size. Zero sizes, as reported
+    /// by a minimized window, are ignored.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) != (self.frame.width, self.frame.height) && width > 0 && height > 0 {
+            self.frame = Framebuffer::new(width, height);
+            self.hires = Framebuffer::supersampled(width, height, SUPERSAMPLE_FACTOR);
+        }
+    }
+
+    /// Draws the 3D scene into `frame`, replacing what was there.
+    pub fn render_frame(&mut self, scene: &Scene) {
+        let camera = scene.camera;
+        let orbit_style = LineStyle { width: ORBIT_LINE_WIDTH, antialiased: self.antialiased_lines };
+        let ship_style = LineStyle { width: SHIP_LINE_WIDTH, antialiased: self.antialiased_lines };
+        let fb = if self.supersampling { &mut self.hires } else { &mut self.frame };
+        fb.clear(0x000000);
+
+        match &self.skybox {
+            Some(skybox) => render_skybox_cubemap(fb, camera, skybox),
+            None => render_skybox(fb, camera, &self.star_field, scene.time),
+        }
+        if scene.spaceship.warping {
+            render_warp_streaks(fb, scene.spaceship.warp_progress);
+        }
+
+        if self.show_grid {
+            render_grid(fb, camera, orbit_style);
+        }
+        if self.show_orbits {
+            for planet in &scene.system.planets {
+                if planet.orbit_radius > 0.0 {
+                    let (radius, eccentricity, inclination) = (planet.orbit_radius, planet.eccentricity, planet.inclination);
+                    render_orbit(fb, radius, eccentricity, inclination, camera, 0x505050, orbit_style);
+                }
+            }
+        }
+
+        if self.show_trajectory && !scene.spaceship.warping {
+            let path = scene.spaceship.predict_trajectory(scene.system, TRAJECTORY_STEPS, TRAJECTORY_STEP);
+            render_trajectory(fb, &path, camera, orbit_style);
+        }
+
+        scene.system.render(fb, camera, scene.time);
+        if self.show_spin_axes {
+            render_spin_axes(fb, &scene.system.planets, camera, orbit_style);
+        }
+
+        // Asteroids share the planets' lighting but nothing eclipses them
+        let lights = scene_lights(scene.system);
+        let camera_pos = camera.get_position();
+        for asteroid in scene.asteroids {
+            let position = asteroid.get_position();
+            if (position - camera_pos).length() > ASTEROID_DRAW_DISTANCE {
+                continue;
+            }
+            render_sphere(fb, &position, asteroid.size, ShaderType::Moon, 0.0, 0.0, camera, scene.time, (None, None), None, &lights, &[]);
+        }
+
+        // After everything solid, so planets and asteroids behind the air show through it
+        for planet in &scene.system.planets {
+            if let Some(color) = planet.shader.atmosphere_color() {
+                render_atmosphere(fb, &planet.position, planet.scale, color, camera, &lights);
+            }
+        }
+
+        for planet in scene.system.planets.iter().filter(|p| p.shader == ShaderType::Sun) {
+            render_sun_glow(fb, &planet.position, planet.scale, camera, scene.time);
+        }
+
+        // The hull would fill the screen from inside the cockpit
+        if camera.mode != CameraMode::Cockpit {
+            render_spaceship(fb, scene.spaceship, camera, ship_style, self.mesh_shading, &lights, self.backface_culling);
+        }
+        scene.exhaust.render(fb, camera);
+        if self.supersampling {
+            self.hires.resolve_into(&mut self.frame);
+        }
+        if self.tone_mapping_enabled {
+            self.tone_mapping.apply(&mut self.frame);
+        }
+        if self.aberration_enabled {
+            postprocess::apply_chromatic_aberration(&mut self.frame, &mut self.scratch, self.aberration_strength);
+        }
+        if self.vignette_enabled {
+            postprocess::apply_vignette(&mut self.frame, self.vignette_strength);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn components(v: Vec3) -> (f32, f32, f32) {
+        (v.x, v.y, v.z)
+    }
+
+    #[test]
+    fn vec3_operators_match_named_methods() {
+        let (a, b) = (Vec3::new(1.5, -2.0, 3.25), Vec3::new(-0.5, 4.0, 2.0));
+        assert_eq!(components(a + b), components(Vec3::add(&a, &b)));
+        assert_eq!(components(a - b), components(Vec3::sub(&a, &b)));
+        assert_eq!(components(a * 2.5), components(Vec3::mul(&a, 2.5)));
+        assert_eq!(components(-a), components(Vec3::mul(&a, -1.0)));
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        // Includes points straddling lattice cell boundaries, where a seam
+        // would show up as a jump
+        const EPSILON: f32 = 1e-3;
+        let mut rng = Rng::new(99);
+        for i in 0..500 {
+            let p = if i % 2 == 0 {
+                Vec3::new(rng.range(-20.0, 20.0), rng.range(-20.0, 20.0), rng.range(-20.0, 20.0))
+            } else {
+                let cell = |r: &mut Rng| r.range_u32(0, 40) as f32 - 20.0 - EPSILON / 2.0;
+                Vec3::new(cell(&mut rng), cell(&mut rng), cell(&mut rng))
+            };
+            let here = noise(p.x, p.y, p.z);
+            for step in [Vec3::new(EPSILON, 0.0, 0.0), Vec3::new(0.0, EPSILON, 0.0), Vec3::new(0.0, 0.0, EPSILON)] {
+                let q = p + step;
+                let difference = (noise(q.x, q.y, q.z) - here).abs();
+                assert!(difference < 0.01, "noise jumps by {} near {:?}", difference, p);
+            }
+        }
+    }
+
+    #[test]
+    fn json_export_round_trips_moons_and_rings() {
+        let system = SolarSystem::built_in();
+        assert!(system.planets.iter().any(|p| !p.moons.is_empty()) && system.planets.iter().any(|p| p.has_rings));
+        let path = std::env::temp_dir().join(format!("proyecto3_system_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        system.to_json(path).unwrap();
+        let loaded = SolarSystem::from_json(path);
+        std::fs::remove_file(path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.planets.len(), system.planets.len());
+        let moon_fields = |moon: &Moon| (moon.orbit_radius, moon.orbit_speed, moon.size, moon.angle);
+        let rgb = |c: Color| (c.r, c.g, c.b);
+        for (original, copy) in system.planets.iter().zip(&loaded.planets) {
+            assert_eq!(copy.name, original.name);
+            let moons: Vec<_> = original.moons.iter().map(moon_fields).collect();
+            assert_eq!(copy.moons.iter().map(moon_fields).collect::<Vec<_>>(), moons, "{}'s moons", original.name);
+            assert_eq!(copy.has_rings, original.has_rings, "{}'s rings", original.name);
+            assert_eq!(rgb(copy.ring_color), rgb(original.ring_color), "{}'s ring color", original.name);
+        }
+    }
+
+    #[test]
+    fn matrix_projection_matches_per_vector_math() {
+        let (width, height) = (800, 600);
+        let mut camera = Camera::new();
+        camera.mode = CameraMode::Free;
+        camera.free_position = Vec3::new(3.0, 7.5, -12.0);
+        camera.free_yaw = 0.6;
+        camera.free_pitch = -0.3;
+        let view_proj = camera.view_projection(width, height);
+
+        // The projection the matrix replaced, worked out from the camera basis
+        let (eye, forward, right) = (camera.get_position(), camera.get_forward(), camera.get_right());
+        let up = right.cross(&forward).normalize();
+        let fov_factor = (camera.fov / 2.0).tan();
+        let aspect = width as f32 / height as f32;
+        let by_vectors = |p: &Vec3| {
+            let relative = *p - eye;
+            let (x, y, z) = (relative.dot(&right), relative.dot(&up), relative.dot(&forward));
+            let screen_x = (width as f32 / 2.0) * (1.0 + x / (z * fov_factor * aspect));
+            let screen_y = (height as f32 / 2.0) * (1.0 - y / (z * fov_factor));
+            (screen_x, screen_y, z)
+        };
+
+        for offset in [Vec3::new(0.0, 0.0, 10.0), Vec3::new(-4.0, 2.5, 30.0), Vec3::new(6.0, -3.0, 15.0), Vec3::new(1.0, 1.0, 200.0)] {
+            let p = eye + right * offset.x + up * offset.y + forward * offset.z;
+            let (x, y, z) = project_vertex(&p, &view_proj, width, height).unwrap();
+            let (ex, ey, ez) = by_vectors(&p);
+            assert!((x - ex).abs() < 0.01 && (y - ey).abs() < 0.01, "({}, {}) vs ({}, {})", x, y, ex, ey);
+            assert!((z - ez).abs() < 1e-3 * ez, "depth {} vs {}", z, ez);
+        }
+        let behind = eye - forward * 5.0;
+        assert!(project_vertex(&behind, &view_proj, width, height).is_none());
+    }
+}
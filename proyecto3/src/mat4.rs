@@ -0,0 +1,118 @@
+use crate::Vec3;
+use std::ops::Mul;
+
+/// Row-major 4×4 transform acting on column vectors, so `a * b` applies `b`
+/// first.
+#[derive(Clone, Copy, Debug)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        let mut result = Mat4::IDENTITY;
+        result.m[0][3] = offset.x;
+        result.m[1][3] = offset.y;
+        result.m[2][3] = offset.z;
+        result
+    }
+
+    /// Roll about Z, then pitch about X, then yaw about Y: the orientation of
+    /// a ship whose nose starts along +Z.
+    pub fn rotation(yaw: f32, pitch: f32, roll: f32) -> Mat4 {
+        let (sy, cy) = yaw.sin_cos();
+        let (sp, cp) = pitch.sin_cos();
+        let (sr, cr) = roll.sin_cos();
+        let about_y = Mat4 {
+            m: [
+                [cy, 0.0, sy, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [-sy, 0.0, cy, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+        let about_x = Mat4 {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, cp, -sp, 0.0],
+                [0.0, sp, cp, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+        let about_z = Mat4 {
+            m: [
+                [cr, -sr, 0.0, 0.0],
+                [sr, cr, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+        about_y * about_x * about_z
+    }
+
+    /// Maps view space to clip space for a vertical `fov`. The clip `w` is
+    /// the view depth, which is what the z-buffer stores.
+    pub fn perspective(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov / 2.0).tan();
+        Mat4 {
+            m: [
+                [f / aspect, 0.0, 0.0, 0.0],
+                [0.0, f, 0.0, 0.0],
+                [0.0, 0.0, (far + near) / (far - near), -2.0 * far * near / (far - near)],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+        }
+    }
+
+    /// World to view space for a camera at `eye` looking at `target`, with
+    /// +X to the right, +Y up and +Z into the screen.
+    pub fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Mat4 {
+        let forward = (*target - *eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(&forward).normalize();
+        Mat4 {
+            m: [
+                [right.x, right.y, right.z, -right.dot(eye)],
+                [up.x, up.y, up.z, -up.dot(eye)],
+                [forward.x, forward.y, forward.z, -forward.dot(eye)],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// `p` as a point (w = 1), returning all four homogeneous components.
+    pub fn transform(&self, p: &Vec3) -> [f32; 4] {
+        let m = &self.m;
+        let row = |r: usize| m[r][0] * p.x + m[r][1] * p.y + m[r][2] * p.z + m[r][3];
+        [row(0), row(1), row(2), row(3)]
+    }
+
+    /// `p` as a point, for affine transforms that leave w at 1.
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let [x, y, z, _] = self.transform(p);
+        Vec3::new(x, y, z)
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut result = [[0.0; 4]; 4];
+        for (r, row) in result.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = (0..4).map(|k| self.m[r][k] * other.m[k][c]).sum();
+            }
+        }
+        Mat4 { m: result }
+    }
+}
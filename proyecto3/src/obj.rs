@@ -0,0 +1,236 @@
+use crate::Vec3;
+use std::fs;
+use std::io;
+
+pub struct Vertex {
+    pub position: Vec3,
+}
+
+pub struct Face {
+    pub vertex_indices: Vec<usize>,
+    /// One index into `Model::normals` per vertex, or `None` when the face
+    /// has no `vn` references
+    pub normal_indices: Option<Vec<usize>>,
+    /// Same as `normal_indices` but into `Model::tex_coords`; `None` for
+    /// `v//vn` tokens
+    pub tex_indices: Option<Vec<usize>>,
+}
+
+pub struct Model {
+    pub vertices: Vec<Vertex>,
+    pub normals: Vec<Vec3>,
+    pub tex_coords: Vec<(f32, f32)>,
+    pub faces: Vec<Face>,
+}
+
+fn parse_error(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_number, message),
+    )
+}
+
+/// Reads the three floats after a `v` or `vn` keyword.
+fn parse_vec3<'a>(parts: impl Iterator<Item = &'a str>, line_number: usize) -> io::Result<Vec3> {
+    let coords: Vec<f32> = parts
+        .take(3)
+        .map(|p| p.parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| parse_error(line_number, "invalid coordinate"))?;
+    if coords.len() < 3 {
+        return Err(parse_error(line_number, "expected 3 coordinates"));
+    }
+    Ok(Vec3::new(coords[0], coords[1], coords[2]))
+}
+
+/// A face's optional attribute indices must be given for all of its vertices
+/// or none of them.
+fn per_vertex(indices: Vec<usize>, vertex_count: usize, line_number: usize, what: &str) -> io::Result<Option<Vec<usize>>> {
+    match indices.len() {
+        0 => Ok(None),
+        n if n == vertex_count => Ok(Some(indices)),
+        _ => Err(parse_error(
+            line_number,
+            &format!("face mixes vertices with and without {}", what),
+        )),
+    }
+}
+
+/// Turns a 1-based OBJ index into a 0-based one. Negative indices count
+/// back from the end of the `count` elements read so far.
+fn resolve_index(text: &str, count: usize, line_number: usize) -> io::Result<usize> {
+    let index = text
+        .parse::<i64>()
+        .map_err(|_| parse_error(line_number, "invalid face index"))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(parse_error(line_number, "face index out of range"));
+    }
+    Ok(resolved as usize)
+}
+
+impl Model {
+    pub fn load_from_file(path: &str) -> io::Result<Model> {
+        Model::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(contents: &str) -> io::Result<Model> {
+        let mut model = Model {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            tex_coords: Vec::new(),
+            faces: Vec::new(),
+        };
+
+        for (i, line) in contents.lines().enumerate() {
+            let line_number = i + 1;
+            let mut parts = line.split_whitespace();
+
+            match parts.next() {
+                Some("v") => {
+                    model.vertices.push(Vertex {
+                        position: parse_vec3(parts, line_number)?,
+                    });
+                }
+                Some("vn") => {
+                    model.normals.push(parse_vec3(parts, line_number)?.normalize());
+                }
+                Some("vt") => {
+                    // A third (w) coordinate is allowed but unused
+                    let coords: Vec<f32> = parts
+                        .take(2)
+                        .map(|p| p.parse::<f32>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| parse_error(line_number, "invalid texture coordinate"))?;
+                    if coords.len() < 2 {
+                        return Err(parse_error(line_number, "expected 2 texture coordinates"));
+                    }
+                    model.tex_coords.push((coords[0], coords[1]));
+                }
+                Some("f") => {
+                    let mut vertex_indices = Vec::new();
+                    let mut tex_indices = Vec::new();
+                    let mut normal_indices = Vec::new();
+                    for token in parts {
+                        // Tokens look like v, v/vt, v//vn or v/vt/vn
+                        let mut components = token.split('/');
+                        let position = components.next().unwrap_or_default();
+                        vertex_indices.push(resolve_index(position, model.vertices.len(), line_number)?);
+                        if let Some(tex) = components.next().filter(|t| !t.is_empty()) {
+                            tex_indices.push(resolve_index(tex, model.tex_coords.len(), line_number)?);
+                        }
+                        if let Some(normal) = components.next().filter(|n| !n.is_empty()) {
+                            normal_indices.push(resolve_index(normal, model.normals.len(), line_number)?);
+                        }
+                    }
+                    if vertex_indices.len() < 3 {
+                        return Err(parse_error(line_number, "face needs at least 3 vertices"));
+                    }
+                    let tex_indices = per_vertex(tex_indices, vertex_indices.len(), line_number, "texture coordinates")?;
+                    let normal_indices = per_vertex(normal_indices, vertex_indices.len(), line_number, "normals")?;
+                    model.faces.push(Face {
+                        vertex_indices,
+                        normal_indices,
+                        tex_indices,
+                    });
+                }
+                // Groups and materials aren't used yet
+                _ => {}
+            }
+        }
+
+        if model.faces.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "model has no faces"));
+        }
+
+        Ok(model)
+    }
+
+    /// Fan-triangulates every polygon around its first vertex, keeping the
+    /// original winding, so each face ends up with exactly three vertices.
+    pub fn triangulate(&mut self) {
+        let mut triangles = Vec::with_capacity(self.faces.len());
+        for face in &self.faces {
+            let indices = &face.vertex_indices;
+            for k in 1..indices.len() - 1 {
+                let corners = [0, k, k + 1];
+                let pick = |list: &Vec<usize>| corners.iter().map(|&c| list[c]).collect();
+                triangles.push(Face {
+                    vertex_indices: pick(indices),
+                    normal_indices: face.normal_indices.as_ref().map(pick),
+                    tex_indices: face.tex_indices.as_ref().map(pick),
+                });
+            }
+        }
+        self.faces = triangles;
+    }
+
+    /// Centers the model on its bounding box and scales it so the longest
+    /// side measures `size` world units.
+    pub fn fit_to_size(&mut self, size: f32) {
+        let mut min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for v in &self.vertices {
+            min = Vec3::new(min.x.min(v.position.x), min.y.min(v.position.y), min.z.min(v.position.z));
+            max = Vec3::new(max.x.max(v.position.x), max.y.max(v.position.y), max.z.max(v.position.z));
+        }
+
+        let extent = max - min;
+        let longest = extent.x.max(extent.y).max(extent.z);
+        if longest <= 0.0 {
+            return;
+        }
+
+        let center = min + extent * 0.5;
+        let scale = size / longest;
+        for v in &mut self.vertices {
+            v.position = (v.position - center) * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+v 1 0 1
+v 1 1 1
+v 0 1 1
+f 1 4 3 2
+f 5 6 7 8
+f 1 2 6 5
+f 2 3 7 6
+f 3 4 8 7
+f 4 1 5 8
+";
+
+    #[test]
+    fn triangulating_a_quad_cube_gives_twelve_triangles() {
+        let mut model = Model::parse(CUBE).unwrap();
+        model.triangulate();
+        assert_eq!(model.faces.len(), 12);
+        assert!(model.faces.iter().all(|face| face.vertex_indices.len() == 3));
+        // Fanned around the first corner, in the original winding
+        assert_eq!(model.faces[0].vertex_indices, [0, 3, 2]);
+        assert_eq!(model.faces[1].vertex_indices, [0, 2, 1]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_latest_vertex() {
+        let model = Model::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf -1 -2 -3\n").unwrap();
+        assert_eq!(model.faces[0].vertex_indices, [3, 2, 1]);
+    }
+
+    #[test]
+    fn negative_index_past_the_first_vertex_is_an_error() {
+        let err = Model::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -1 -2 -4\n").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 4: face index out of range");
+    }
+}
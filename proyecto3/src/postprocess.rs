@@ -0,0 +1,101 @@
+use crate::Framebuffer;
+
+// Brightness the Reinhard curve treats as 1 before compressing; above 1 it
+// lifts the darks harder and rolls the highlights off sooner
+const REINHARD_EXPOSURE: f32 = 1.5;
+// Fraction of the way from the center to a corner where the vignette starts
+const VIGNETTE_START: f32 = 0.35;
+// How far red and blue are pulled apart at full chromatic aberration, as a
+// fraction of each pixel's distance from the center
+const ABERRATION_MAX_SHIFT: f32 = 0.006;
+
+/// Display curve applied to each channel of the finished frame: an optional
+/// Reinhard tone-map, then gamma.
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapping {
+    /// Channels go out as `c^(1/gamma)`, so above 1 brightens the shadows
+    /// and 1 leaves them as the shaders wrote them
+    pub gamma: f32,
+    pub reinhard: bool,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping { gamma: 1.2, reinhard: true }
+    }
+}
+
+impl ToneMapping {
+    /// `channel` in 0..=1 mapped through the curve.
+    pub fn map(&self, channel: f32) -> f32 {
+        let c = if self.reinhard {
+            // Scaled so white stays white: c·e / (1 + c·e), over 1·e / (1 + e)
+            let exposed = channel * REINHARD_EXPOSURE;
+            exposed / (1.0 + exposed) * (1.0 + REINHARD_EXPOSURE) / REINHARD_EXPOSURE
+        } else {
+            channel
+        };
+        c.clamp(0.0, 1.0).powf(1.0 / self.gamma.max(0.01))
+    }
+
+    /// Runs every pixel of `fb` through the curve. Channels only have 256
+    /// values, so the curve is worked out once per value and looked up.
+    pub fn apply(&self, fb: &mut Framebuffer) {
+        let mut table = [0u32; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            *entry = (self.map(value as f32 / 255.0) * 255.0).round() as u32;
+        }
+        for pixel in &mut fb.color {
+            let (r, g, b) = ((*pixel >> 16) & 0xFF, (*pixel >> 8) & 0xFF, *pixel & 0xFF);
+            *pixel = (table[r as usize] << 16) | (table[g as usize] << 8) | table[b as usize];
+        }
+    }
+}
+
+/// Darkens `fb` toward its edges by up to `strength` (0..=1) at the
+/// corners. The falloff starts `VIGNETTE_START` of the way out from the
+/// center and eases in with a smoothstep, so there's no visible edge.
+pub fn apply_vignette(fb: &mut Framebuffer, strength: f32) {
+    let (cx, cy) = (fb.width as f32 / 2.0, fb.height as f32 / 2.0);
+    let corner = (cx * cx + cy * cy).sqrt();
+    let strength = strength.clamp(0.0, 1.0);
+    for y in 0..fb.height {
+        let dy = y as f32 + 0.5 - cy;
+        for x in 0..fb.width {
+            let dx = x as f32 + 0.5 - cx;
+            let t = (((dx * dx + dy * dy).sqrt() / corner - VIGNETTE_START) / (1.0 - VIGNETTE_START)).clamp(0.0, 1.0);
+            let factor = 1.0 - strength * t * t * (3.0 - 2.0 * t);
+            let pixel = &mut fb.color[y * fb.width + x];
+            let scale = |shift: u32| ((((*pixel >> shift) & 0xFF) as f32 * factor) as u32) << shift;
+            *pixel = scale(16) | scale(8) | scale(0);
+        }
+    }
+}
+
+/// Splits red and blue apart toward the edges like a cheap lens: red is
+/// read from slightly farther out along the line from the center, blue
+/// from slightly farther in, and green stays put. At full `strength` the
+/// corners are `ABERRATION_MAX_SHIFT` of the way out of register. The
+/// channels are read from `scratch`, a copy of the frame, so the pass never
+/// samples a pixel it has already shifted.
+pub fn apply_chromatic_aberration(fb: &mut Framebuffer, scratch: &mut Vec<u32>, strength: f32) {
+    scratch.clear();
+    scratch.extend_from_slice(&fb.color);
+    let (cx, cy) = (fb.width as f32 / 2.0, fb.height as f32 / 2.0);
+    let shift = strength.clamp(0.0, 1.0) * ABERRATION_MAX_SHIFT;
+    let (max_x, max_y) = (fb.width as f32 - 1.0, fb.height as f32 - 1.0);
+    let sample = |x: f32, y: f32, scale: f32| {
+        let sx = (cx + (x - cx) * scale).round().clamp(0.0, max_x) as usize;
+        let sy = (cy + (y - cy) * scale).round().clamp(0.0, max_y) as usize;
+        scratch[sy * fb.width + sx]
+    };
+    for y in 0..fb.height {
+        for x in 0..fb.width {
+            let (px, py) = (x as f32, y as f32);
+            let red = sample(px, py, 1.0 + shift) & 0xFF0000;
+            let blue = sample(px, py, 1.0 - shift) & 0x0000FF;
+            let idx = y * fb.width + x;
+            fb.color[idx] = red | (scratch[idx] & 0x00FF00) | blue;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// The linear congruential generator behind the star field, exhaust
+/// particles and procedural scenes. Same seed, same sequence.
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Folds a 64-bit user seed into the generator's 32-bit state.
+    pub fn from_seed(seed: u64) -> Self {
+        Rng::new((seed ^ (seed >> 32)) as u32)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345);
+        self.state
+    }
+
+    /// Uniform in [0, 1), taken from the better-mixed high bits.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform integer in `min..=max`.
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        min + (self.next_f32() * (max - min + 1) as f32) as u32
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{Planet, Pose, Spaceship, Vec3};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+
+// Plain-text save file, one record per line:
+//   time <seconds>
+//   ship <px> <py> <pz> <vx> <vy> <vz> <yaw> <pitch> <roll> <health> <fuel>
+//   planet <orbit_angle> <rotation> [<moon angle>...]
+//   explored [<planet index>...]
+// Planet lines are in the same order as the scene's planet list. Saves from
+// before exploration was tracked have no explored line and load as none.
+//
+// Waypoints live in their own file so they outlast any one save:
+//   waypoint <px> <py> <pz> <yaw> <pitch>
+
+fn invalid(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_number, message),
+    )
+}
+
+pub fn save_state(path: &str, time: f32, spaceship: &Spaceship, planets: &[Planet]) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    writeln!(writer, "time {}", time)?;
+    let (p, v) = (spaceship.position, spaceship.velocity);
+    writeln!(
+        writer,
+        "ship {} {} {} {} {} {} {} {} {} {} {}",
+        p.x, p.y, p.z, v.x, v.y, v.z, spaceship.yaw, spaceship.pitch, spaceship.roll, spaceship.health, spaceship.fuel
+    )?;
+    for planet in planets {
+        write!(writer, "planet {} {}", planet.orbit_angle, planet.rotation)?;
+        for moon in &planet.moons {
+            write!(writer, " {}", moon.angle)?;
+        }
+        writeln!(writer)?;
+    }
+    // Sorted so the same progress always writes the same file
+    let mut explored: Vec<_> = spaceship.explored.iter().collect();
+    explored.sort_unstable();
+    write!(writer, "explored")?;
+    for index in explored {
+        write!(writer, " {}", index)?;
+    }
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Restores a file written by `save_state` and returns the saved simulation
+/// time. Nothing is modified unless the whole file parses and matches the
+/// current planet and moon counts.
+pub fn load_state(path: &str, spaceship: &mut Spaceship, planets: &mut [Planet]) -> io::Result<f32> {
+    let contents = fs::read_to_string(path)?;
+    let mut time = None;
+    let mut ship = None;
+    let mut planet_states = Vec::new();
+    let mut explored = HashSet::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let mut parts = line.split_whitespace();
+        let Some(kind) = parts.next() else {
+            continue;
+        };
+        let values: Vec<f32> = parts
+            .map(|p| p.parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid(line_number, "invalid number"))?;
+
+        match kind {
+            "time" if values.len() == 1 => time = Some(values[0]),
+            "ship" if values.len() == 11 => ship = Some(values),
+            "planet" if values.len() >= 2 => planet_states.push((line_number, values)),
+            "explored" => {
+                for value in values {
+                    if value < 0.0 || value.fract() != 0.0 || value as usize >= planets.len() {
+                        return Err(invalid(line_number, "explored planet index out of range"));
+                    }
+                    explored.insert(value as usize);
+                }
+            }
+            "time" | "ship" | "planet" => return Err(invalid(line_number, "wrong number of values")),
+            _ => return Err(invalid(line_number, "unknown record")),
+        }
+    }
+
+    let ship = ship.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing ship record"))?;
+    if planet_states.len() != planets.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("save has {} planets, scene has {}", planet_states.len(), planets.len()),
+        ));
+    }
+    for ((line_number, values), planet) in planet_states.iter().zip(planets.iter()) {
+        if values.len() != 2 + planet.moons.len() {
+            return Err(invalid(*line_number, "moon count doesn't match the scene"));
+        }
+    }
+
+    for ((_, values), planet) in planet_states.iter().zip(planets.iter_mut()) {
+        planet.orbit_angle = values[0];
+        planet.rotation = values[1];
+        for (moon, &angle) in planet.moons.iter_mut().zip(&values[2..]) {
+            moon.angle = angle;
+        }
+        // A zero step recomputes the position from the restored angle
+        planet.update(0.0);
+    }
+
+    spaceship.position = Vec3::new(ship[0], ship[1], ship[2]);
+    spaceship.velocity = Vec3::new(ship[3], ship[4], ship[5]);
+    spaceship.yaw = ship[6];
+    spaceship.pitch = ship[7];
+    spaceship.roll = ship[8];
+    spaceship.target_roll = ship[8];
+    spaceship.health = ship[9];
+    spaceship.fuel = ship[10];
+    spaceship.warping = false;
+    spaceship.explored = explored;
+
+    Ok(time.unwrap_or(0.0))
+}
+
+pub fn save_waypoints(path: &str, waypoints: &[Pose]) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    for waypoint in waypoints {
+        let p = waypoint.position;
+        writeln!(writer, "waypoint {} {} {} {} {}", p.x, p.y, p.z, waypoint.yaw, waypoint.pitch)?;
+    }
+    writer.flush()
+}
+
+pub fn load_waypoints(path: &str) -> io::Result<Vec<Pose>> {
+    let contents = fs::read_to_string(path)?;
+    let mut waypoints = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let mut parts = line.split_whitespace();
+        let Some(kind) = parts.next() else {
+            continue;
+        };
+        if kind != "waypoint" {
+            return Err(invalid(line_number, "unknown record"));
+        }
+        let values: Vec<f32> = parts
+            .map(|p| p.parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid(line_number, "invalid number"))?;
+        if values.len() != 5 {
+            return Err(invalid(line_number, "wrong number of values"));
+        }
+        waypoints.push(Pose {
+            position: Vec3::new(values[0], values[1], values[2]),
+            yaw: values[3],
+            pitch: values[4],
+        });
+    }
+
+    Ok(waypoints)
+}
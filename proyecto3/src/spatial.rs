@@ -0,0 +1,65 @@
+use crate::{Planet, Vec3};
+use std::collections::HashMap;
+
+/// Uniform grid bucketing planet indices by the cell their center falls in,
+/// so proximity queries only look at the cells around a point instead of
+/// every planet. Planets move every frame, so it gets rebuilt after each
+/// orbit update.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+    /// Largest planet radius in the grid, so surface queries can widen
+    /// their reach to cover planets centered in a neighboring cell
+    max_radius: f32,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32, planets: &[Planet]) -> SpatialGrid {
+        let mut grid = SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            max_radius: 0.0,
+        };
+        grid.rebuild(planets);
+        grid
+    }
+
+    pub fn rebuild(&mut self, planets: &[Planet]) {
+        self.cells.clear();
+        self.max_radius = 0.0;
+        for (i, planet) in planets.iter().enumerate() {
+            self.cells.entry(self.cell_of(&planet.position)).or_default().push(i);
+            self.max_radius = self.max_radius.max(planet.scale);
+        }
+    }
+
+    pub fn max_radius(&self) -> f32 {
+        self.max_radius
+    }
+
+    fn cell_of(&self, p: &Vec3) -> (i32, i32, i32) {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+        (cell(p.x), cell(p.y), cell(p.z))
+    }
+
+    /// Indices of every planet whose center may lie within `radius` of
+    /// `pos`, in ascending order. Callers still do the exact distance test.
+    pub fn nearby(&self, pos: &Vec3, radius: f32) -> Vec<usize> {
+        let reach = Vec3::new(radius, radius, radius);
+        let (min, max) = (self.cell_of(&(*pos - reach)), self.cell_of(&(*pos + reach)));
+        let mut found = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    if let Some(bucket) = self.cells.get(&(x, y, z)) {
+                        found.extend_from_slice(bucket);
+                    }
+                }
+            }
+        }
+        // Same order as a scan over the planet list, so ties and sums come
+        // out exactly as before
+        found.sort_unstable();
+        found
+    }
+}
@@ -0,0 +1,75 @@
+use crate::image;
+use crate::Color;
+use std::io;
+
+/// An image wrapped around a sphere, equirectangular: u runs west to east
+/// around the equator and v from the north pole down to the south.
+pub struct Texture {
+    /// Where the image was loaded from, so scenes can be written back out
+    pub path: String,
+    width: usize,
+    height: usize,
+    texels: Vec<u32>,
+    average: Color,
+}
+
+impl Texture {
+    pub fn load(path: &str) -> io::Result<Texture> {
+        let (texels, width, height) = image::load_image(path)?;
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty texture"));
+        }
+        let mut sums = [0u64; 3];
+        for &pixel in &texels {
+            sums[0] += (pixel >> 16 & 0xFF) as u64;
+            sums[1] += (pixel >> 8 & 0xFF) as u64;
+            sums[2] += (pixel & 0xFF) as u64;
+        }
+        let count = texels.len() as u64;
+        let average = Color::new((sums[0] / count) as u8, (sums[1] / count) as u8, (sums[2] / count) as u8);
+        Ok(Texture {
+            path: path.to_string(),
+            width,
+            height,
+            texels,
+            average,
+        })
+    }
+
+    /// Mean color of the whole image.
+    pub fn average(&self) -> Color {
+        self.average
+    }
+
+    /// Bilinear lookup with (0, 0) at the top-left corner. u wraps, so the
+    /// seam blends the left and right edges; v stops at the poles.
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        self.filter(u, v, true)
+    }
+
+    /// Bilinear lookup that stops at every edge, for images that don't
+    /// tile, like the faces of a cubemap.
+    pub fn sample_clamped(&self, u: f32, v: f32) -> Color {
+        self.filter(u, v, false)
+    }
+
+    fn filter(&self, u: f32, v: f32, wrap_u: bool) -> Color {
+        // Texel centers sit half a texel in from each edge
+        let x = u * self.width as f32 - 0.5;
+        let x = if wrap_u { x } else { x.clamp(0.0, self.width as f32 - 1.0) };
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, self.height as f32 - 1.0);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let x0 = (x.floor() as i64).rem_euclid(self.width as i64) as usize;
+        let x1 = if wrap_u { (x0 + 1) % self.width } else { (x0 + 1).min(self.width - 1) };
+        let y0 = y as usize;
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let texel = |x: usize, y: usize| {
+            let pixel = self.texels[y * self.width + x];
+            Color::new((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+        };
+        let top = texel(x0, y0).lerp(&texel(x1, y0), fx);
+        let bottom = texel(x0, y1).lerp(&texel(x1, y1), fx);
+        top.lerp(&bottom, fy)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::framebuffer::Framebuffer;
+use crate::Color;
+
+/// Screen-space vertex as returned by `project_vertex`: (x, y, depth)
+pub type ScreenVertex = (f32, f32, f32);
+
+fn edge(a: &ScreenVertex, b: &ScreenVertex, px: f32, py: f32) -> f32 {
+    (b.0 - a.0) * (py - a.1) - (b.1 - a.1) * (px - a.0)
+}
+
+// Top-left fill rule: pixels exactly on a shared edge belong to only one of
+// the two triangles, so adjacent faces leave no gaps and no double writes
+fn is_top_left(a: &ScreenVertex, b: &ScreenVertex) -> bool {
+    (a.1 == b.1 && b.0 > a.0) || b.1 < a.1
+}
+
+/// Calls `shade` with the barycentric weights of v1, v2 and v3 for every
+/// pixel the triangle covers, and depth-tests the color it returns.
+fn rasterize(fb: &mut Framebuffer, v1: ScreenVertex, v2: ScreenVertex, v3: ScreenVertex, shade: impl Fn(f32, f32, f32) -> u32) {
+    let mut area = edge(&v1, &v2, v3.0, v3.1);
+    if area.abs() < 1e-6 {
+        return;
+    }
+
+    // Normalize winding so the interior is always where every edge is positive
+    let flipped = area < 0.0;
+    let (v2, v3) = if flipped {
+        area = -area;
+        (v3, v2)
+    } else {
+        (v2, v3)
+    };
+
+    let min_x = v1.0.min(v2.0).min(v3.0).floor().max(0.0) as i32;
+    let max_x = v1.0.max(v2.0).max(v3.0).ceil().min(fb.width as f32 - 1.0) as i32;
+    let min_y = v1.1.min(v2.1).min(v3.1).floor().max(0.0) as i32;
+    let max_y = v1.1.max(v2.1).max(v3.1).ceil().min(fb.height as f32 - 1.0) as i32;
+
+    // A pixel on an edge is inside only when that edge is a top or left one
+    let (top_left1, top_left2, top_left3) = (is_top_left(&v2, &v3), is_top_left(&v3, &v1), is_top_left(&v1, &v2));
+    let inside = |w: f32, top_left: bool| if top_left { w >= 0.0 } else { w > 0.0 };
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+
+            let w1 = edge(&v2, &v3, px, py);
+            let w2 = edge(&v3, &v1, px, py);
+            let w3 = edge(&v1, &v2, px, py);
+
+            if !(inside(w1, top_left1) && inside(w2, top_left2) && inside(w3, top_left3)) {
+                continue;
+            }
+
+            let z = (w1 * v1.2 + w2 * v2.2 + w3 * v3.2) / area;
+            let (b1, b2, b3) = (w1 / area, w2 / area, w3 / area);
+            let color = if flipped { shade(b1, b3, b2) } else { shade(b1, b2, b3) };
+            fb.plot(x, y, z, color);
+        }
+    }
+}
+
+pub fn triangle_filled(
+    fb: &mut Framebuffer,
+    v1: ScreenVertex,
+    v2: ScreenVertex,
+    v3: ScreenVertex,
+    color: u32,
+) {
+    rasterize(fb, v1, v2, v3, |_, _, _| color);
+}
+
+/// Fills the triangle blending the three corner colors across it.
+pub fn triangle_gouraud(
+    fb: &mut Framebuffer,
+    (v1, c1): (ScreenVertex, Color),
+    (v2, c2): (ScreenVertex, Color),
+    (v3, c3): (ScreenVertex, Color),
+) {
+    let channel = |c: Color| [c.r as f32, c.g as f32, c.b as f32];
+    let (c1, c2, c3) = (channel(c1), channel(c2), channel(c3));
+    rasterize(fb, v1, v2, v3, |b1, b2, b3| {
+        let mix = |i: usize| (c1[i] * b1 + c2[i] * b2 + c3[i] * b3).clamp(0.0, 255.0) as u32;
+        (mix(0) << 16) | (mix(1) << 8) | mix(2)
+    });
+}